@@ -0,0 +1,30 @@
+use std::time::Instant;
+
+const RECORD_COUNT: u32 = 10_000;
+
+/// Times reading a file made up entirely of maximum-length S3 records
+/// (255-byte byte count, 250 data bytes each). Run with `cargo bench`.
+fn main() {
+    let records = (0..RECORD_COUNT)
+        .map(|i| {
+            srec::Record::S3(srec::Data {
+                address: srec::Address32(i * 250),
+                data: (0..250).map(|b| b as u8).collect(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let s = srec::generate_srec_file(&records).unwrap();
+
+    let start = Instant::now();
+    let count = srec::read_records(&s).map(Result::unwrap).count();
+    let elapsed = start.elapsed();
+
+    assert_eq!(count, RECORD_COUNT as usize);
+    println!(
+        "read {} maximum-length records ({} bytes) in {:?} ({:.1} MB/s)",
+        count,
+        s.len(),
+        elapsed,
+        s.len() as f64 / elapsed.as_secs_f64() / 1_000_000.0
+    );
+}
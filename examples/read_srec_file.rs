@@ -48,6 +48,9 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                 srec::Record::S9(addr) => {
                     println!("S9 16-bit start address: addr = {:#06X}", u32::from(addr))
                 }
+                srec::Record::Unknown { t, bytes } => {
+                    println!("S{} unknown record: bytes = {:02X?}", t, bytes)
+                }
             },
             Err(err) => println!("error reading record: {}", err),
         }
@@ -0,0 +1,34 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+// Builds records of every shape from arbitrary bytes, including types and
+// lengths which cannot be encoded, and checks each is either rejected with an
+// error or encoded with valid framing
+fuzz_target!(|data: &[u8]| {
+    let (&t, bytes) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+
+    let records = [
+        srec::Record::Unknown {
+            t,
+            bytes: bytes.to_vec(),
+        },
+        srec::Record::S0(String::from_utf8_lossy(bytes).into_owned()),
+        srec::Record::S3(srec::Data {
+            address: srec::Address32(0xffff_fff0),
+            data: bytes.to_vec(),
+        }),
+    ];
+
+    for record in &records {
+        let mut encoded = String::new();
+        if record.encode_to(&mut encoded).is_err() {
+            assert!(encoded.is_empty());
+            continue;
+        }
+
+        assert!(srec::reader::raw_record_from_str(&encoded).is_ok());
+    }
+});
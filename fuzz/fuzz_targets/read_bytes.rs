@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+// Reads arbitrary bytes, which need not be UTF-8, through each of the byte
+// and stream based readers
+fuzz_target!(|data: &[u8]| {
+    for _ in srec::reader::read_records_from_bytes(data) {}
+    for _ in srec::reader::read_records_from_reader(data) {}
+
+    let _ = srec::analyze::stats_streaming(data);
+});
@@ -0,0 +1,18 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+// Reads arbitrary input, and checks every record which parses successfully
+// survives being encoded and read back
+fuzz_target!(|data: &[u8]| {
+    let s = match std::str::from_utf8(data) {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+
+    for record in srec::read_records(s).flatten() {
+        let encoded = srec::generate_srec_file(&[record.clone()]).unwrap();
+        let decoded = srec::read_records(&encoded).collect::<Vec<_>>();
+
+        assert_eq!(decoded, vec![Ok(record)]);
+    }
+});
@@ -0,0 +1,1164 @@
+//! Analysis of SREC files
+use crate::image::Segment;
+use crate::reader::{read_records_from_reader, Error};
+use crate::record::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::iter::FromIterator;
+use std::mem;
+
+/// Summary statistics of the records in an SREC file
+///
+/// Accumulated one record at a time with [`add`](Self::add), or collected
+/// directly from the results of any of the reading functions. Also available
+/// as [`reader::Stats`](crate::reader::Stats).
+///
+/// # Examples
+///
+/// ```rust
+/// let stats: srec::analyze::FileStats =
+///     srec::read_records("S00600004844521B\nS107123400010203AC\nS107123400010203FF\nS9031234B6\n")
+///         .collect();
+///
+/// assert_eq!(stats.records_by_type[1], 1);
+/// assert_eq!(stats.records_by_type[9], 1);
+/// assert_eq!(stats.min_address, Some(0x1234));
+/// assert_eq!(stats.max_address, Some(0x1237));
+/// assert_eq!(stats.data_bytes, 4);
+/// assert_eq!(stats.errors, 1);
+/// ```
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub struct FileStats {
+    /// Number of records read of each type, indexed by record type (e.g.
+    /// `records_by_type[1]` is the number of S1 records)
+    pub records_by_type: [usize; 10],
+    /// Number of records read of unknown type outside 0 to 9, which have no
+    /// entry in `records_by_type`
+    pub other_records: usize,
+    /// Lowest address written by a data record
+    pub min_address: Option<u32>,
+    /// Highest address written by a data record
+    pub max_address: Option<u32>,
+    /// Total number of bytes in all data records
+    pub data_bytes: u64,
+    /// Number of lines which could not be read as a record
+    pub errors: usize,
+}
+
+impl FileStats {
+    /// Updates the statistics with the result of reading a single record
+    pub fn add(&mut self, record: &Result<Record, Error>) {
+        match record {
+            Ok(record) => self.add_record(record),
+            Err(_) => self.errors += 1,
+        }
+    }
+
+    /// Updates the statistics with a record which was read
+    fn add_record(&mut self, record: &Record) {
+        let (t, data) = match record {
+            Record::S0(_) => (0, None),
+            Record::S1(Data { address, data }) => (1, Some((u32::from(*address), data))),
+            Record::S2(Data { address, data }) => (2, Some((u32::from(*address), data))),
+            Record::S3(Data { address, data }) => (3, Some((u32::from(*address), data))),
+            Record::S5(_) => (5, None),
+            Record::S6(_) => (6, None),
+            Record::S7(_) => (7, None),
+            Record::S8(_) => (8, None),
+            Record::S9(_) => (9, None),
+            Record::Unknown { t, .. } => (*t as usize, None),
+        };
+
+        match self.records_by_type.get_mut(t) {
+            Some(n) => *n += 1,
+            None => self.other_records += 1,
+        }
+
+        if let Some((address, data)) = data {
+            self.data_bytes += data.len() as u64;
+
+            if !data.is_empty() {
+                let end = address.saturating_add(data.len() as u32 - 1);
+
+                self.min_address = Some(self.min_address.map_or(address, |a| a.min(address)));
+                self.max_address = Some(self.max_address.map_or(end, |a| a.max(end)));
+            }
+        }
+    }
+
+    /// Returns the total number of records read
+    pub fn record_count(&self) -> usize {
+        self.records_by_type.iter().sum::<usize>() + self.other_records
+    }
+
+    /// Returns the total number of data (S1, S2 and S3) records read
+    pub fn data_record_count(&self) -> usize {
+        self.records_by_type[1..=3].iter().sum()
+    }
+}
+
+impl<'a> Extend<&'a Result<Record, Error>> for FileStats {
+    fn extend<I: IntoIterator<Item = &'a Result<Record, Error>>>(&mut self, iter: I) {
+        for record in iter {
+            self.add(record);
+        }
+    }
+}
+
+impl<E> Extend<Result<Record, E>> for FileStats {
+    fn extend<I: IntoIterator<Item = Result<Record, E>>>(&mut self, iter: I) {
+        for record in iter {
+            match record {
+                Ok(record) => self.add_record(&record),
+                Err(_) => self.errors += 1,
+            }
+        }
+    }
+}
+
+impl<E> FromIterator<Result<Record, E>> for FileStats {
+    fn from_iter<I: IntoIterator<Item = Result<Record, E>>>(iter: I) -> Self {
+        let mut stats = FileStats::default();
+        stats.extend(iter);
+        stats
+    }
+}
+
+/// Computes statistics over the records read from `r` one line at a time,
+/// without storing the records
+///
+/// Memory use is bounded by the longest line in the input, so files far larger
+/// than available memory can be audited. Lines are read as by
+/// [`read_records_from_reader`], so overlong lines are not buffered whole.
+/// Only errors from `r` itself are returned - lines which fail to parse,
+/// including overlong lines and lines which are not UTF-8, are counted in
+/// [`FileStats::errors`].
+///
+/// # Examples
+///
+/// ```rust
+/// let s = "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n";
+///
+/// let stats = srec::analyze::stats_streaming(s.as_bytes()).unwrap();
+///
+/// assert_eq!(stats.data_record_count(), 2);
+/// assert_eq!(stats.data_bytes, 8);
+/// assert_eq!(stats.min_address, Some(0x1234));
+/// assert_eq!(stats.max_address, Some(0x123b));
+/// ```
+pub fn stats_streaming<R: BufRead>(r: R) -> io::Result<FileStats> {
+    let mut stats = FileStats::default();
+
+    for record in read_records_from_reader(r) {
+        match record {
+            Ok(record) => stats.add_record(&record),
+            Err(crate::Error::Io(err)) => return Err(err),
+            Err(_) => stats.errors += 1,
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Irregular data record lengths found in a file
+///
+/// Toolchains generally write data in records of one fixed length, with a
+/// shorter record wherever a contiguous run of data ends. Some pad, merge or
+/// split records differently (e.g. 28 byte records, or lengths which vary
+/// through the file), which makes otherwise identical images produce
+/// different files.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct LengthQuirks {
+    /// Number of data records of each length
+    pub lengths: BTreeMap<usize, usize>,
+    /// Most common data record length, ignoring the last record of each
+    /// contiguous run (the larger length is chosen if tied)
+    pub typical_len: Option<usize>,
+    /// Indices of data records which are not the last of their contiguous run
+    /// but whose length differs from [`typical_len`](Self::typical_len)
+    pub irregular: Vec<usize>,
+}
+
+impl LengthQuirks {
+    /// Returns true if every data record other than the last of each run has
+    /// the typical length
+    pub fn is_uniform(&self) -> bool {
+        self.irregular.is_empty()
+    }
+}
+
+/// Data record with its type and address width discarded
+struct Run<'a> {
+    index: usize,
+    address: u32,
+    data: &'a [u8],
+}
+
+/// Returns the data records of `records`, and whether each ends a contiguous
+/// run of data
+fn data_runs(records: &[Record]) -> Vec<(Run<'_>, bool)> {
+    let mut runs: Vec<(Run<'_>, bool)> = Vec::new();
+    let mut previous: Option<&Record> = None;
+
+    for (index, record) in records.iter().enumerate() {
+        let (address, data) = match record.memory_effect() {
+            MemoryEffect::Write { address, data } => (address, data),
+            _ => {
+                previous = None;
+                continue;
+            }
+        };
+
+        if let Some((last, ends_run)) = runs.last_mut() {
+            let contiguous = previous
+                .is_some_and(|p| mem::discriminant(p) == mem::discriminant(record))
+                && last.address as u64 + last.data.len() as u64 == address as u64;
+            *ends_run = !contiguous;
+        }
+
+        runs.push((
+            Run {
+                index,
+                address,
+                data,
+            },
+            true,
+        ));
+        previous = Some(record);
+    }
+
+    runs
+}
+
+/// Finds data records with irregular lengths
+///
+/// # Examples
+///
+/// ```rust
+/// let records = srec::read_records(
+///     "S1061234000102B0\nS104123703AF\nS1061238040506A0\nS104123B07A7\nS9031234B6\n",
+/// )
+/// .collect::<Result<Vec<_>, _>>()
+/// .unwrap();
+///
+/// let quirks = srec::analyze::length_quirks(&records);
+///
+/// assert_eq!(quirks.typical_len, Some(3));
+/// assert_eq!(quirks.irregular, [1]);
+/// ```
+pub fn length_quirks(records: &[Record]) -> LengthQuirks {
+    let runs = data_runs(records);
+    let mut quirks = LengthQuirks::default();
+    let mut inner_lengths = BTreeMap::new();
+
+    for (run, ends_run) in &runs {
+        *quirks.lengths.entry(run.data.len()).or_insert(0) += 1;
+        if !ends_run {
+            *inner_lengths.entry(run.data.len()).or_insert(0) += 1;
+        }
+    }
+
+    quirks.typical_len = inner_lengths
+        .iter()
+        .max_by_key(|(&len, &count)| (count, len))
+        .map(|(&len, _)| len);
+
+    if let Some(typical_len) = quirks.typical_len {
+        quirks.irregular = runs
+            .iter()
+            .filter(|(run, ends_run)| !ends_run && run.data.len() != typical_len)
+            .map(|(run, _)| run.index)
+            .collect();
+    }
+
+    quirks
+}
+
+/// Returns a data record of the same type as `like` with a new address and
+/// data
+fn data_record_like(like: &Record, address: u32, data: Vec<u8>) -> Record {
+    match like {
+        Record::S1(_) => Record::S1(Data {
+            address: Address16(address as u16),
+            data,
+        }),
+        Record::S2(_) => Record::S2(Data {
+            address: Address24(address),
+            data,
+        }),
+        _ => Record::S3(Data {
+            address: Address32(address),
+            data,
+        }),
+    }
+}
+
+/// Rewrites the data records so each contiguous run of data is split into
+/// records of exactly `len` bytes, except for the last record of each run
+///
+/// Returns the rewritten records along with the quirks found in the input, so
+/// what was normalized can be reported. Data records keep their type and
+/// their position relative to other records. Count records (S5 and S6) are
+/// updated to the new number of data records, with an S5 record becoming S6
+/// if the count needs 24 bits. A count record which cannot hold the new count
+/// is dropped rather than left with a stale value. A `len` of 0 is treated as
+/// 1.
+///
+/// # Examples
+///
+/// ```rust
+/// let records = srec::read_records(
+///     "S1061234000102B0\nS104123703AF\nS1061238040506A0\nS104123B07A7\nS9031234B6\n",
+/// )
+/// .collect::<Result<Vec<_>, _>>()
+/// .unwrap();
+///
+/// let (normalized, quirks) = srec::analyze::normalize_lengths(&records, 4);
+///
+/// assert_eq!(quirks.irregular, [1]);
+/// assert_eq!(
+///     normalized[..2],
+///     [
+///         srec::Record::S1(srec::Data {
+///             address: srec::Address16(0x1234),
+///             data: vec![0x00, 0x01, 0x02, 0x03],
+///         }),
+///         srec::Record::S1(srec::Data {
+///             address: srec::Address16(0x1238),
+///             data: vec![0x04, 0x05, 0x06, 0x07],
+///         }),
+///     ]
+/// );
+/// ```
+pub fn normalize_lengths(records: &[Record], len: usize) -> (Vec<Record>, LengthQuirks) {
+    let len = len.max(1);
+    let quirks = length_quirks(records);
+    let run_ends = data_runs(records)
+        .into_iter()
+        .filter(|(_, ends_run)| *ends_run)
+        .map(|(run, _)| run.index)
+        .collect::<BTreeSet<_>>();
+
+    let mut normalized = Vec::with_capacity(records.len());
+    let mut run: Option<(u32, Vec<u8>)> = None;
+
+    for (index, record) in records.iter().enumerate() {
+        let (address, data) = match record.memory_effect() {
+            MemoryEffect::Write { address, data } => (address, data),
+            _ => {
+                normalized.push(record.clone());
+                continue;
+            }
+        };
+
+        run.get_or_insert_with(|| (address, Vec::new()))
+            .1
+            .extend_from_slice(data);
+
+        if !run_ends.contains(&index) {
+            continue;
+        }
+
+        if let Some((start, buffer)) = run.take() {
+            if buffer.is_empty() {
+                normalized.push(data_record_like(record, start, buffer));
+                continue;
+            }
+
+            for (i, chunk) in buffer.chunks(len).enumerate() {
+                let address = start.wrapping_add((i * len) as u32);
+                normalized.push(data_record_like(record, address, chunk.to_vec()));
+            }
+        }
+    }
+
+    let count = normalized
+        .iter()
+        .filter(|r| matches!(r.memory_effect(), MemoryEffect::Write { .. }))
+        .count();
+
+    let normalized = normalized
+        .into_iter()
+        .filter_map(|record| recount(record, count))
+        .collect();
+
+    (normalized, quirks)
+}
+
+/// Updates a count record (S5 or S6) to `count`, returning `None` if it cannot
+/// hold it, and returns any other record unchanged
+fn recount(record: Record, count: usize) -> Option<Record> {
+    match record {
+        Record::S5(_) if count <= 0xffff => Some(Record::S5(Count16(count as u16))),
+        Record::S5(_) | Record::S6(_) if count <= 0xff_ffff => {
+            Some(Record::S6(Count24(count as u32)))
+        }
+        Record::S5(_) | Record::S6(_) => None,
+        record => Some(record),
+    }
+}
+
+/// Where count records (S5 and S6) are accepted in a file, and what their
+/// count is checked against
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub enum CountPolicy {
+    /// At most one count record, after every data record, giving the number
+    /// of data records in the file
+    #[default]
+    EndOnly,
+    /// Count records anywhere in the file (e.g. before the data), each giving
+    /// the number of data records in the whole file
+    Anywhere,
+    /// Count records anywhere in the file, each giving the number of data
+    /// records before it, for tools which emit them periodically
+    Checkpoint,
+}
+
+/// Error returned when a count record does not meet a [`CountPolicy`]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum CountError {
+    /// Count record was somewhere the policy does not accept one
+    Misplaced {
+        /// Index of the count record
+        index: usize,
+    },
+    /// Count record's count differed from the number of data records
+    Mismatch {
+        /// Index of the count record
+        index: usize,
+        /// Number of data records the policy expected to be counted
+        expected: usize,
+        /// Count given by the record
+        found: u32,
+    },
+}
+
+impl error::Error for CountError {}
+
+impl fmt::Display for CountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CountError::Misplaced { index } => {
+                write!(f, "record {}: misplaced count record", index)
+            }
+            CountError::Mismatch {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "record {}: count record gives {} data records, expected {}",
+                index, found, expected
+            ),
+        }
+    }
+}
+
+/// Checks the count records (S5 and S6) of a file against `policy`, returning
+/// the first which does not meet it
+///
+/// A file without count records always passes.
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::analyze::{check_counts, CountError, CountPolicy};
+/// use srec::{Address16, Count16, Data, Record};
+///
+/// let records = [
+///     Record::S5(Count16(1)),
+///     Record::S1(Data {
+///         address: Address16(0x1234),
+///         data: vec![0x00],
+///     }),
+/// ];
+///
+/// assert_eq!(
+///     check_counts(&records, CountPolicy::EndOnly),
+///     Err(CountError::Misplaced { index: 0 })
+/// );
+/// assert_eq!(check_counts(&records, CountPolicy::Anywhere), Ok(()));
+/// ```
+pub fn check_counts(records: &[Record], policy: CountPolicy) -> Result<(), CountError> {
+    let is_data = |r: &Record| matches!(r.memory_effect(), MemoryEffect::Write { .. });
+    let total = records.iter().filter(|r| is_data(r)).count();
+    let mut seen_data = 0;
+    let mut seen_count = false;
+
+    for (index, record) in records.iter().enumerate() {
+        let found = match record.memory_effect() {
+            MemoryEffect::Write { .. } => {
+                seen_data += 1;
+                continue;
+            }
+            MemoryEffect::Count(found) => found,
+            _ => continue,
+        };
+
+        let expected = match policy {
+            CountPolicy::EndOnly if seen_count || seen_data != total => {
+                return Err(CountError::Misplaced { index });
+            }
+            CountPolicy::EndOnly | CountPolicy::Anywhere => total,
+            CountPolicy::Checkpoint => seen_data,
+        };
+
+        if found as usize != expected {
+            return Err(CountError::Mismatch {
+                index,
+                expected,
+                found,
+            });
+        }
+
+        seen_count = true;
+    }
+
+    Ok(())
+}
+
+/// Whether a page found by [`dedupe_pages`] needs to be sent
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum PageRef {
+    /// First page with this content
+    Unique,
+    /// Same content as the earlier page at this address
+    SameAs(u32),
+}
+
+/// Splits data into aligned pages of `page_size` bytes and finds the pages
+/// whose content repeats an earlier page, e.g. blank or repeated calibration
+/// pages
+///
+/// Returns every page holding any data, in ascending address order, with
+/// whether it is the first page of its content. Bytes of a page not written
+/// by any segment are taken as 0xFF (erased flash), and where segments overlap
+/// the later one is taken. Pages are compared by the data written to them, so
+/// memory use does not depend on `page_size`. A `page_size` of 0 is treated as
+/// 1.
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::analyze::{dedupe_pages, PageRef};
+///
+/// let file = srec::SrecFile::parse(
+///     "S107000000010203F2\nS107000400010203EE\nS1070008AAAAAAAA48\n",
+/// )
+/// .unwrap();
+///
+/// let pages = dedupe_pages(file.data_segments(), 4);
+///
+/// assert_eq!(
+///     pages,
+///     [(0x0, PageRef::Unique), (0x4, PageRef::SameAs(0x0)), (0x8, PageRef::Unique)]
+/// );
+/// ```
+pub fn dedupe_pages(segments: &[Segment], page_size: u32) -> Vec<(u32, PageRef)> {
+    let page_size = page_size.max(1);
+    let mut pages: BTreeMap<u32, Vec<(usize, &[u8])>> = BTreeMap::new();
+
+    for segment in segments {
+        let mut address = segment.address as u64;
+        let mut data = &segment.data[..];
+
+        while !data.is_empty() {
+            let base = address - address % page_size as u64;
+            let offset = (address - base) as usize;
+            let n = data.len().min(page_size as usize - offset);
+
+            pages
+                .entry(base as u32)
+                .or_default()
+                .push((offset, &data[..n]));
+
+            address += n as u64;
+            data = &data[n..];
+        }
+    }
+
+    let pages = pages
+        .into_iter()
+        .map(|(base, pieces)| (base, page_runs(&pieces)))
+        .collect::<Vec<_>>();
+    let mut first_with_content: HashMap<&[(usize, Vec<u8>)], u32> = HashMap::new();
+
+    pages
+        .iter()
+        .map(|(base, runs)| match first_with_content.get(&runs[..]) {
+            Some(&first) => (*base, PageRef::SameAs(first)),
+            None => {
+                first_with_content.insert(runs, *base);
+                (*base, PageRef::Unique)
+            }
+        })
+        .collect()
+}
+
+/// Returns the content of a page as its runs of bytes other than 0xFF, with
+/// their offsets, given the data written to it in order
+///
+/// Two pages have the same content exactly when they have the same runs. Only
+/// pieces which overlap or touch are merged into a buffer, so no more is
+/// allocated than the data written.
+fn page_runs(pieces: &[(usize, &[u8])]) -> Vec<(usize, Vec<u8>)> {
+    let mut order = (0..pieces.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| pieces[i].0);
+
+    let mut runs = Vec::new();
+    let mut i = 0;
+
+    while i < order.len() {
+        let start = pieces[order[i]].0;
+        let mut end = start;
+        let mut j = i;
+        while j < order.len() && pieces[order[j]].0 <= end {
+            let (offset, data) = pieces[order[j]];
+            end = end.max(offset + data.len());
+            j += 1;
+        }
+
+        // Write the pieces in their original order, so later pieces win where
+        // they overlap
+        let mut members = order[i..j].to_vec();
+        members.sort_unstable();
+        let mut buffer = vec![0xff; end - start];
+        for k in members {
+            let (offset, data) = pieces[k];
+            buffer[offset - start..offset - start + data.len()].copy_from_slice(data);
+        }
+
+        let mut offset = start;
+        for run in buffer.split(|&b| b == 0xff) {
+            if !run.is_empty() {
+                runs.push((offset, run.to_vec()));
+            }
+            offset += run.len() + 1;
+        }
+
+        i = j;
+    }
+
+    runs
+}
+
+/// Range of addresses written by two data records, found by
+/// [`detect_overlaps`]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct OverlapReport {
+    /// Address of the first byte written by both records
+    pub address: u32,
+    /// Index of the earlier of the two records
+    pub first: usize,
+    /// Index of the later of the two records
+    pub second: usize,
+    /// Bytes the earlier record writes to the overlapping range
+    pub first_data: Vec<u8>,
+    /// Bytes the later record writes to the overlapping range
+    pub second_data: Vec<u8>,
+}
+
+impl OverlapReport {
+    /// Returns true if the two records write different values to the range,
+    /// rather than the same data twice
+    pub fn is_conflict(&self) -> bool {
+        self.first_data != self.second_data
+    }
+}
+
+/// Finds every pair of data records which write to the same addresses
+///
+/// Reading records does not check for overlaps, and when loading, later
+/// records silently overwrite earlier ones. Reports are sorted by address,
+/// then by record index, and give the bytes each record writes so duplicates
+/// can be told apart from conflicts.
+///
+/// # Examples
+///
+/// ```rust
+/// let records = srec::read_records("S107123400010203AC\nS1051236AABB4D\n")
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+///
+/// let overlaps = srec::analyze::detect_overlaps(&records);
+///
+/// assert_eq!(overlaps.len(), 1);
+/// assert_eq!(overlaps[0].address, 0x1236);
+/// assert_eq!(overlaps[0].first_data, [0x02, 0x03]);
+/// assert_eq!(overlaps[0].second_data, [0xaa, 0xbb]);
+/// assert!(overlaps[0].is_conflict());
+/// ```
+pub fn detect_overlaps(records: &[Record]) -> Vec<OverlapReport> {
+    let mut writes = records
+        .iter()
+        .enumerate()
+        .filter_map(|(index, record)| match record.memory_effect() {
+            MemoryEffect::Write { address, data } if !data.is_empty() => {
+                Some((address as u64, index, data))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    writes.sort_unstable_by_key(|&(start, index, _)| (start, index));
+
+    let mut reports = Vec::new();
+    let mut active: Vec<(u64, usize, &[u8])> = Vec::new();
+
+    for (start, index, data) in writes {
+        active.retain(|&(a_start, _, a_data)| a_start + a_data.len() as u64 > start);
+
+        for &(a_start, a_index, a_data) in &active {
+            let end = (a_start + a_data.len() as u64).min(start + data.len() as u64);
+            let overlap = |s: u64, d: &[u8]| d[(start - s) as usize..(end - s) as usize].to_vec();
+
+            let (first, second) = if a_index < index {
+                ((a_index, a_start, a_data), (index, start, data))
+            } else {
+                ((index, start, data), (a_index, a_start, a_data))
+            };
+
+            reports.push(OverlapReport {
+                address: start as u32,
+                first: first.0,
+                second: second.0,
+                first_data: overlap(first.1, first.2),
+                second_data: overlap(second.1, second.2),
+            });
+        }
+
+        active.push((start, index, data));
+    }
+
+    reports.sort_by_key(|r| (r.address, r.first, r.second));
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_stats_add_data_record_updates_address_range_and_byte_count() {
+        let mut stats = FileStats::default();
+
+        stats.add(&Ok(Record::S1(Data {
+            address: Address16(0x1234),
+            data: vec![0x00, 0x01, 0x02, 0x03],
+        })));
+
+        assert_eq!(stats.records_by_type[1], 1);
+        assert_eq!(stats.min_address, Some(0x1234));
+        assert_eq!(stats.max_address, Some(0x1237));
+        assert_eq!(stats.data_bytes, 4);
+    }
+
+    #[test]
+    fn file_stats_add_empty_data_record_does_not_update_address_range() {
+        let mut stats = FileStats::default();
+
+        stats.add(&Ok(Record::S3(Data {
+            address: Address32(0x12345678),
+            data: vec![],
+        })));
+
+        assert_eq!(stats.records_by_type[3], 1);
+        assert_eq!(stats.min_address, None);
+        assert_eq!(stats.max_address, None);
+    }
+
+    #[test]
+    fn file_stats_add_error_increments_errors() {
+        let mut stats = FileStats::default();
+
+        stats.add(&Err(Error::ChecksumMismatch {
+            t: 1,
+            address: Some(0x1234),
+            found: 0xff,
+            computed: 0xac,
+        }));
+
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.record_count(), 0);
+    }
+
+    #[test]
+    fn file_stats_empty_data_record_does_not_set_addresses() {
+        let records = vec![
+            Ok::<_, Error>(Record::S3(Data {
+                address: Address32(0x1000),
+                data: vec![],
+            })),
+            Ok(Record::S3(Data {
+                address: Address32(0xffff_fffe),
+                data: vec![0x00, 0x01],
+            })),
+        ];
+
+        let stats = records.into_iter().collect::<FileStats>();
+
+        assert_eq!(stats.records_by_type[3], 2);
+        assert_eq!(stats.record_count(), 2);
+        assert_eq!(stats.min_address, Some(0xffff_fffe));
+        assert_eq!(stats.max_address, Some(0xffff_ffff));
+        assert_eq!(stats.data_bytes, 2);
+    }
+
+    #[test]
+    fn file_stats_unknown_type_above_9_counts_as_other_record() {
+        let mut stats = FileStats::default();
+
+        stats.add(&Ok(Record::Unknown {
+            t: 12,
+            bytes: vec![0x00],
+        }));
+
+        assert_eq!(stats.records_by_type, [0; 10]);
+        assert_eq!(stats.other_records, 1);
+        assert_eq!(stats.record_count(), 1);
+    }
+
+    #[test]
+    fn stats_streaming_empty_input_returns_default_stats() {
+        let stats = stats_streaming("".as_bytes()).unwrap();
+
+        assert_eq!(stats, FileStats::default());
+    }
+
+    #[test]
+    fn stats_streaming_multiple_lines_returns_correct_stats() {
+        let s =
+            "S00600004844521B\r\n\r\nS107123400010203AC\r\nS2081234560001020355\r\nS9031234B4\r\n";
+
+        let stats = stats_streaming(s.as_bytes()).unwrap();
+
+        assert_eq!(
+            stats,
+            FileStats {
+                records_by_type: [1, 1, 1, 0, 0, 0, 0, 0, 0, 0],
+                other_records: 0,
+                min_address: Some(0x1234),
+                max_address: Some(0x123459),
+                data_bytes: 8,
+                errors: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn stats_streaming_bad_lines_count_as_errors() {
+        let mut s = b"S1\xff\xfe\nS9031234B6\n".to_vec();
+        s.extend_from_slice(&[b'0'; 100_000]);
+
+        let stats = stats_streaming(&s[..]).unwrap();
+
+        assert_eq!(stats.record_count(), 1);
+        assert_eq!(stats.errors, 2);
+    }
+
+    #[test]
+    fn stats_streaming_matches_stats_of_read_records() {
+        let s = "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n";
+        let mut stats = FileStats::default();
+
+        stats.extend(&crate::reader::read_records(s).collect::<Vec<_>>());
+
+        assert_eq!(stats_streaming(s.as_bytes()).unwrap(), stats);
+    }
+
+    fn s2(address: u32, data: &[u8]) -> Record {
+        Record::S2(Data {
+            address: Address24(address),
+            data: data.to_vec(),
+        })
+    }
+
+    #[test]
+    fn length_quirks_short_records_at_end_of_runs_are_not_irregular() {
+        let records = [
+            s2(0x1000, &[0; 4]),
+            s2(0x1004, &[0; 4]),
+            s2(0x1008, &[0; 2]),
+            s2(0x2000, &[0; 4]),
+            s2(0x2004, &[0; 1]),
+        ];
+
+        let quirks = length_quirks(&records);
+
+        assert_eq!(quirks.typical_len, Some(4));
+        assert!(quirks.is_uniform());
+        assert_eq!(
+            quirks.lengths,
+            [(1, 1), (2, 1), (4, 3)].iter().cloned().collect()
+        );
+    }
+
+    #[test]
+    fn length_quirks_count_record_breaks_run() {
+        let records = [
+            s2(0x1000, &[0; 4]),
+            s2(0x1004, &[0; 2]),
+            Record::S5(Count16(2)),
+            s2(0x1006, &[0; 4]),
+        ];
+
+        let quirks = length_quirks(&records);
+
+        assert_eq!(quirks.typical_len, Some(4));
+        assert!(quirks.is_uniform());
+    }
+
+    #[test]
+    fn normalize_lengths_rechunks_runs_and_updates_count() {
+        let records = [
+            Record::S0("HDR".into()),
+            s2(0x1000, &[0, 1, 2]),
+            s2(0x1003, &[3, 4, 5, 6, 7]),
+            s2(0x3000, &[]),
+            Record::S5(Count16(3)),
+            Record::S8(Address24(0x1000)),
+        ];
+
+        let (normalized, quirks) = normalize_lengths(&records, 4);
+
+        assert_eq!(quirks.typical_len, Some(3));
+        assert_eq!(
+            normalized,
+            [
+                Record::S0("HDR".into()),
+                s2(0x1000, &[0, 1, 2, 3]),
+                s2(0x1004, &[4, 5, 6, 7]),
+                s2(0x3000, &[]),
+                Record::S5(Count16(3)),
+                Record::S8(Address24(0x1000)),
+            ]
+        );
+    }
+
+    #[test]
+    fn normalize_lengths_already_uniform_returns_same_records() {
+        let records = [
+            s2(0x1000, &[0; 4]),
+            s2(0x1004, &[0; 4]),
+            s2(0x1008, &[0; 1]),
+        ];
+
+        let (normalized, quirks) = normalize_lengths(&records, 4);
+
+        assert!(quirks.is_uniform());
+        assert_eq!(normalized, records);
+    }
+
+    #[test]
+    fn normalize_lengths_count_above_16_bits_returns_s6_count() {
+        let records = [s2(0x1000, &[0; 0x10000]), Record::S5(Count16(1))];
+
+        let (normalized, _) = normalize_lengths(&records, 1);
+
+        assert_eq!(normalized.len(), 0x10001);
+        assert_eq!(normalized[0x10000], Record::S6(Count24(0x10000)));
+    }
+
+    #[test]
+    fn recount_count_above_24_bits_drops_count_record() {
+        assert_eq!(recount(Record::S5(Count16(1)), 0x100_0000), None);
+        assert_eq!(recount(Record::S6(Count24(1)), 0x100_0000), None);
+    }
+
+    fn counted_file(count_at: &[usize], counts: &[u16]) -> Vec<Record> {
+        let mut records = (0..3).map(|i| s2(i * 4, &[0x00])).collect::<Vec<_>>();
+        for (&at, &count) in count_at.iter().zip(counts).rev() {
+            records.insert(at, Record::S5(Count16(count)));
+        }
+        records
+    }
+
+    #[test]
+    fn check_counts_end_only_trailing_count_returns_ok() {
+        let records = counted_file(&[3], &[3]);
+
+        let r = check_counts(&records, CountPolicy::EndOnly);
+
+        assert_eq!(r, Ok(()));
+    }
+
+    #[test]
+    fn check_counts_end_only_second_count_returns_err_misplaced() {
+        let records = counted_file(&[3, 3], &[3, 3]);
+
+        let r = check_counts(&records, CountPolicy::EndOnly);
+
+        assert_eq!(r, Err(CountError::Misplaced { index: 4 }));
+    }
+
+    #[test]
+    fn check_counts_anywhere_leading_count_checks_total() {
+        let records = counted_file(&[0], &[2]);
+
+        let r = check_counts(&records, CountPolicy::Anywhere);
+
+        assert_eq!(
+            r,
+            Err(CountError::Mismatch {
+                index: 0,
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn check_counts_checkpoint_checks_data_records_so_far() {
+        let records = counted_file(&[1, 3], &[1, 3]);
+
+        let r = check_counts(&records, CountPolicy::Checkpoint);
+        let r2 = check_counts(&records, CountPolicy::Anywhere);
+
+        assert_eq!(r, Ok(()));
+        assert_eq!(
+            r2,
+            Err(CountError::Mismatch {
+                index: 1,
+                expected: 3,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn dedupe_pages_unaligned_segments_fill_gaps_with_erased_bytes() {
+        let segments = [
+            Segment {
+                address: 0x12,
+                data: vec![0x00, 0x01, 0x02],
+            },
+            Segment {
+                address: 0x22,
+                data: vec![0x00, 0x01],
+            },
+            Segment {
+                address: 0x25,
+                data: vec![0x02],
+            },
+        ];
+
+        let pages = dedupe_pages(&segments, 4);
+
+        assert_eq!(
+            pages,
+            [
+                (0x10, PageRef::Unique),
+                (0x14, PageRef::Unique),
+                (0x20, PageRef::SameAs(0x10)),
+                (0x24, PageRef::Unique),
+            ]
+        );
+    }
+
+    #[test]
+    fn dedupe_pages_matching_pages_refer_to_first() {
+        let segments = [Segment {
+            address: 0xffff_fff0,
+            data: vec![0xff; 0x10],
+        }];
+
+        let pages = dedupe_pages(&segments, 8);
+
+        assert_eq!(
+            pages,
+            [
+                (0xffff_fff0, PageRef::Unique),
+                (0xffff_fff8, PageRef::SameAs(0xffff_fff0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn dedupe_pages_huge_page_size_compares_written_data_only() {
+        let segments = [
+            Segment {
+                address: 0x10,
+                data: vec![0x00, 0x01],
+            },
+            Segment {
+                address: 0x8000_0010,
+                data: vec![0x00, 0x01],
+            },
+        ];
+
+        let pages = dedupe_pages(&segments, 0x8000_0000);
+
+        assert_eq!(
+            pages,
+            [(0x0, PageRef::Unique), (0x8000_0000, PageRef::SameAs(0x0))]
+        );
+    }
+
+    #[test]
+    fn dedupe_pages_overlapping_and_touching_segments_match_merged_segment() {
+        let segments = [
+            Segment {
+                address: 0x0,
+                data: vec![0x00, 0x00, 0x02],
+            },
+            Segment {
+                address: 0x1,
+                data: vec![0x01],
+            },
+            Segment {
+                address: 0x3,
+                data: vec![0x03],
+            },
+            Segment {
+                address: 0x4,
+                data: vec![0x00, 0x01, 0x02, 0x03],
+            },
+        ];
+
+        let pages = dedupe_pages(&segments, 4);
+
+        assert_eq!(pages, [(0x0, PageRef::Unique), (0x4, PageRef::SameAs(0x0))]);
+    }
+
+    #[test]
+    fn detect_overlaps_reports_each_pair_in_file_order() {
+        let records = [
+            s2(0x14, &[0x04, 0x05]),
+            s2(0x10, &[0x00, 0x01, 0x02, 0x03, 0x04, 0x05]),
+            Record::S5(Count16(3)),
+            s2(0x15, &[0x05, 0x06]),
+            s2(0x20, &[0x20]),
+        ];
+
+        let overlaps = detect_overlaps(&records);
+
+        assert_eq!(
+            overlaps,
+            [
+                OverlapReport {
+                    address: 0x14,
+                    first: 0,
+                    second: 1,
+                    first_data: vec![0x04, 0x05],
+                    second_data: vec![0x04, 0x05],
+                },
+                OverlapReport {
+                    address: 0x15,
+                    first: 0,
+                    second: 3,
+                    first_data: vec![0x05],
+                    second_data: vec![0x05],
+                },
+                OverlapReport {
+                    address: 0x15,
+                    first: 1,
+                    second: 3,
+                    first_data: vec![0x05],
+                    second_data: vec![0x05],
+                },
+            ]
+        );
+        assert!(overlaps.iter().all(|r| !r.is_conflict()));
+    }
+}
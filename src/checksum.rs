@@ -1,7 +1,11 @@
 use std::num::Wrapping;
 
-pub fn checksum_of(data: &[u8]) -> u8 {
-    !data.iter().map(|b| Wrapping(*b)).sum::<Wrapping<u8>>().0
+pub fn checksum_of<'a>(data: impl IntoIterator<Item = &'a u8>) -> u8 {
+    !data
+        .into_iter()
+        .map(|b| Wrapping(*b))
+        .sum::<Wrapping<u8>>()
+        .0
 }
 
 #[cfg(test)]
@@ -58,5 +62,4 @@ mod tests {
 
         assert_eq!(checksum_of(&vec![0x03, 0x00, 0x00]), 0xfc);
     }
-
 }
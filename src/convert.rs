@@ -0,0 +1,411 @@
+//! Conversion of SREC files to other formats
+use crate::error::Error;
+use crate::reader::read_records_from_reader;
+use crate::record::*;
+use std::io::{self, BufRead, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Writes `len` copies of `fill` to `w` without allocating a buffer of that
+/// size
+fn write_fill<W: Write>(w: &mut W, fill: u8, len: u64) -> io::Result<()> {
+    let chunk = [fill; 4096];
+    let mut remaining = len;
+
+    while remaining > 0 {
+        let n = remaining.min(chunk.len() as u64) as usize;
+        w.write_all(&chunk[..n])?;
+        remaining -= n as u64;
+    }
+
+    Ok(())
+}
+
+/// Options controlling how [`srec_to_bin_stream_with`] converts a file
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct ConvertOptions {
+    /// Address of the byte written at offset 0 of the output
+    pub base: u32,
+    /// Byte written to gaps between records
+    pub fill: u8,
+    /// Largest output allowed, in bytes. A data record which would extend the
+    /// output beyond this is reported as [`Error::SpanTooLarge`] before
+    /// anything is written for it, so a single stray record at a high address
+    /// cannot cause gigabytes of fill to be written.
+    pub max_span_bytes: Option<u64>,
+}
+
+impl Default for ConvertOptions {
+    /// Returns options with a base of 0, a fill of 0xFF (erased flash) and no
+    /// limit on the output size
+    fn default() -> Self {
+        ConvertOptions {
+            base: 0,
+            fill: 0xff,
+            max_span_bytes: None,
+        }
+    }
+}
+
+/// Converts SREC text read from `r` to a flat binary written to `w`, decoding
+/// one line at a time
+///
+/// The byte for address `base` is written at offset 0 of `w`, so `w` should be
+/// empty when passed. Data records may appear in any order: `w` is seeked to
+/// each record's offset, and any gap between the end of the output so far and
+/// a new record is filled with `fill`. Later records overwrite earlier ones
+/// where they overlap.
+///
+/// Memory use does not depend on the size of the input or output, so very
+/// large files can be converted. Lines are read as by
+/// [`read_records_from_reader`], so overlong lines are reported as
+/// [`LineTooLong`](crate::reader::Error::LineTooLong) without being buffered
+/// whole. Non-data records are ignored.
+///
+/// Returns the length of the binary written. Stops at the first line which
+/// cannot be read as a record, or at the first data record with an address
+/// below `base`.
+///
+/// # Examples
+///
+/// ```rust
+/// let s = "S00600004844521B\nS10712380405060798\nS107123400010203AC\nS9031234B6\n";
+/// let mut bin = std::io::Cursor::new(Vec::new());
+///
+/// let len = srec::convert::srec_to_bin_stream(s.as_bytes(), &mut bin, 0x1230, 0xff).unwrap();
+///
+/// assert_eq!(len, 12);
+/// assert_eq!(
+///     bin.into_inner(),
+///     [0xff, 0xff, 0xff, 0xff, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07]
+/// );
+/// ```
+pub fn srec_to_bin_stream<R: BufRead, W: Write + Seek>(
+    r: R,
+    w: W,
+    base: u32,
+    fill: u8,
+) -> Result<u64, Error> {
+    let options = ConvertOptions {
+        base,
+        fill,
+        max_span_bytes: None,
+    };
+
+    srec_to_bin_stream_with(r, w, &options)
+}
+
+/// Converts SREC text read from `r` to a flat binary written to `w` using the
+/// provided options
+///
+/// Otherwise behaves exactly as [`srec_to_bin_stream`].
+///
+/// # Examples
+///
+/// ```rust
+/// let s = "S107123400010203AC\nS30900F0000004050607F0\n";
+/// let mut bin = std::io::Cursor::new(Vec::new());
+/// let options = srec::convert::ConvertOptions {
+///     base: 0x1234,
+///     max_span_bytes: Some(1024 * 1024),
+///     ..Default::default()
+/// };
+///
+/// let r = srec::convert::srec_to_bin_stream_with(s.as_bytes(), &mut bin, &options);
+///
+/// assert!(matches!(r, Err(srec::Error::SpanTooLarge { line: 2, .. })));
+/// ```
+pub fn srec_to_bin_stream_with<R: BufRead, W: Write + Seek>(
+    r: R,
+    w: W,
+    options: &ConvertOptions,
+) -> Result<u64, Error> {
+    convert(r, w, options, None)
+}
+
+/// Converts SREC text read from `r` to a flat binary written to `w` using the
+/// provided options, stopping early if `cancel` is set
+///
+/// `cancel` is checked before each line is read, exactly as by
+/// [`ReadRecords::with_cancel_flag`](crate::reader::ReadRecords::with_cancel_flag),
+/// so another thread (e.g. a GUI's cancel button) can abort the conversion of
+/// a huge file promptly, in which case [`Error::Cancelled`] is returned.
+/// Whatever was written before then is left in `w`. Otherwise behaves exactly
+/// as [`srec_to_bin_stream_with`].
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::atomic::AtomicBool;
+/// use std::sync::Arc;
+///
+/// let s = "S107123400010203AC\n";
+/// let mut bin = std::io::Cursor::new(Vec::new());
+/// let options = srec::convert::ConvertOptions::default();
+///
+/// let r = srec::convert::srec_to_bin_stream_cancellable(
+///     s.as_bytes(),
+///     &mut bin,
+///     &options,
+///     Arc::new(AtomicBool::new(true)),
+/// );
+///
+/// assert!(matches!(r, Err(srec::Error::Cancelled)));
+/// ```
+pub fn srec_to_bin_stream_cancellable<R: BufRead, W: Write + Seek>(
+    r: R,
+    w: W,
+    options: &ConvertOptions,
+    cancel: Arc<AtomicBool>,
+) -> Result<u64, Error> {
+    convert(r, w, options, Some(&cancel))
+}
+
+fn convert<R: BufRead, W: Write + Seek>(
+    r: R,
+    mut w: W,
+    options: &ConvertOptions,
+    cancel: Option<&AtomicBool>,
+) -> Result<u64, Error> {
+    let ConvertOptions {
+        base,
+        fill,
+        max_span_bytes,
+    } = *options;
+    let mut records = read_records_from_reader(r);
+    let mut len = 0u64;
+
+    loop {
+        if cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+            return Err(Error::Cancelled);
+        }
+
+        let record = match records.next() {
+            Some(record) => record,
+            None => break,
+        };
+        let record = record?;
+        let line_number = records.line_number();
+
+        let (address, data) = match record.memory_effect() {
+            MemoryEffect::Write { address, data } if !data.is_empty() => (address, data),
+            _ => continue,
+        };
+
+        if address < base {
+            return Err(Error::AddressBelowBase {
+                line: line_number,
+                address,
+            });
+        }
+
+        let offset = (address - base) as u64;
+        let end = offset + data.len() as u64;
+
+        if max_span_bytes.is_some_and(|max| end > max) {
+            return Err(Error::SpanTooLarge {
+                line: line_number,
+                span: end,
+            });
+        }
+
+        if offset > len {
+            w.seek(SeekFrom::Start(len))?;
+            write_fill(&mut w, fill, offset - len)?;
+        } else {
+            w.seek(SeekFrom::Start(offset))?;
+        }
+
+        w.write_all(data)?;
+        len = len.max(end);
+    }
+
+    w.flush()?;
+
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader;
+    use std::io::Cursor;
+
+    #[test]
+    fn srec_to_bin_stream_empty_input_writes_nothing() {
+        let mut w = Cursor::new(Vec::new());
+
+        let len = srec_to_bin_stream("".as_bytes(), &mut w, 0, 0xff);
+
+        assert_eq!(len.unwrap(), 0);
+        assert!(w.into_inner().is_empty());
+    }
+
+    #[test]
+    fn srec_to_bin_stream_contiguous_records_writes_data() {
+        let s = "S107123400010203AC\nS10712380405060798\n";
+        let mut w = Cursor::new(Vec::new());
+
+        let len = srec_to_bin_stream(s.as_bytes(), &mut w, 0x1234, 0xff);
+
+        assert_eq!(len.unwrap(), 8);
+        assert_eq!(
+            w.into_inner(),
+            [0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07]
+        );
+    }
+
+    #[test]
+    fn srec_to_bin_stream_gap_between_records_is_filled() {
+        let s = "S107123400010203AC\nS107123C0405060794\n";
+        let mut w = Cursor::new(Vec::new());
+
+        let len = srec_to_bin_stream(s.as_bytes(), &mut w, 0x1234, 0xee);
+
+        assert_eq!(len.unwrap(), 12);
+        assert_eq!(
+            w.into_inner(),
+            [0x00, 0x01, 0x02, 0x03, 0xee, 0xee, 0xee, 0xee, 0x04, 0x05, 0x06, 0x07]
+        );
+    }
+
+    #[test]
+    fn srec_to_bin_stream_out_of_order_records_fill_earlier_gap() {
+        let s = "S10712380405060798\nS107123400010203AC\n";
+        let mut w = Cursor::new(Vec::new());
+
+        let len = srec_to_bin_stream(s.as_bytes(), &mut w, 0x1234, 0xff);
+
+        assert_eq!(len.unwrap(), 8);
+        assert_eq!(
+            w.into_inner(),
+            [0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07]
+        );
+    }
+
+    #[test]
+    fn srec_to_bin_stream_overlapping_records_later_wins() {
+        let s = "S107123400010203AC\nS1051236AABB4D\n";
+        let mut w = Cursor::new(Vec::new());
+
+        let len = srec_to_bin_stream(s.as_bytes(), &mut w, 0x1234, 0xff);
+
+        assert_eq!(len.unwrap(), 4);
+        assert_eq!(w.into_inner(), [0x00, 0x01, 0xaa, 0xbb]);
+    }
+
+    #[test]
+    fn srec_to_bin_stream_address_below_base_returns_err_address_below_base() {
+        let s = "S00600004844521B\nS107123400010203AC\n";
+        let mut w = Cursor::new(Vec::new());
+
+        let len = srec_to_bin_stream(s.as_bytes(), &mut w, 0x1235, 0xff);
+
+        assert!(matches!(
+            len,
+            Err(Error::AddressBelowBase {
+                line: 2,
+                address: 0x1234
+            })
+        ));
+    }
+
+    #[test]
+    fn srec_to_bin_stream_invalid_record_returns_err_syntax() {
+        let s = "S00600004844521B\n\nS107123400010203FF\n";
+        let mut w = Cursor::new(Vec::new());
+
+        let len = srec_to_bin_stream(s.as_bytes(), &mut w, 0, 0xff);
+
+        assert!(matches!(
+            len,
+            Err(Error::Syntax {
+                line: 3,
+                error: reader::Error::ChecksumMismatch { .. }
+            })
+        ));
+    }
+
+    #[test]
+    fn srec_to_bin_stream_with_record_beyond_max_span_returns_err_span_too_large() {
+        let s = "S107123400010203AC\nS107133400010203AB\n";
+        let mut w = Cursor::new(Vec::new());
+        let options = ConvertOptions {
+            base: 0x1234,
+            max_span_bytes: Some(0x100),
+            ..Default::default()
+        };
+
+        let len = srec_to_bin_stream_with(s.as_bytes(), &mut w, &options);
+
+        assert!(matches!(
+            len,
+            Err(Error::SpanTooLarge {
+                line: 2,
+                span: 0x104
+            })
+        ));
+        assert_eq!(w.into_inner().len(), 4);
+    }
+
+    #[test]
+    fn srec_to_bin_stream_with_span_at_limit_returns_ok() {
+        let s = "S107123400010203AC\nS107123800010203A8\n";
+        let mut w = Cursor::new(Vec::new());
+        let options = ConvertOptions {
+            base: 0x1234,
+            max_span_bytes: Some(8),
+            ..Default::default()
+        };
+
+        let len = srec_to_bin_stream_with(s.as_bytes(), &mut w, &options);
+
+        assert_eq!(len.unwrap(), 8);
+    }
+
+    /// Reader giving one line per read, which sets a flag once the first line
+    /// has been read
+    struct CancelAfterFirstLine {
+        lines: std::vec::IntoIter<&'static [u8]>,
+        cancel: Arc<AtomicBool>,
+    }
+
+    impl io::Read for CancelAfterFirstLine {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.cancel.store(true, Ordering::Relaxed);
+
+            let line = self.lines.next().unwrap_or_default();
+            buf[..line.len()].copy_from_slice(line);
+            Ok(line.len())
+        }
+    }
+
+    #[test]
+    fn srec_to_bin_stream_cancellable_stops_between_lines() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let r = io::BufReader::new(CancelAfterFirstLine {
+            lines: vec![&b"S107123400010203AC\n"[..], &b"S107123800010203A8\n"[..]].into_iter(),
+            cancel: cancel.clone(),
+        });
+        let mut w = Cursor::new(Vec::new());
+        let options = ConvertOptions {
+            base: 0x1234,
+            ..Default::default()
+        };
+
+        let len = srec_to_bin_stream_cancellable(r, &mut w, &options, cancel);
+
+        assert!(matches!(len, Err(Error::Cancelled)));
+        assert_eq!(w.into_inner(), [0x00, 0x01, 0x02, 0x03]);
+    }
+
+    #[test]
+    fn srec_to_bin_stream_non_utf8_line_returns_err_syntax() {
+        let s = b"S107123400010203AC\nS1\xff\xfe\n";
+        let mut w = Cursor::new(Vec::new());
+
+        let len = srec_to_bin_stream(&s[..], &mut w, 0x1234, 0xff);
+
+        assert!(matches!(len, Err(Error::Syntax { line: 2, .. })));
+    }
+}
@@ -0,0 +1,160 @@
+//! Checksums and CRCs for digests of image data
+//!
+//! Loaders and bootloaders commonly verify an image against one of a handful
+//! of digests. These are implemented here without dependencies, so they are
+//! available however the crate is built. Each function computes the digest of
+//! a byte slice in one call.
+
+/// Generates the 256 entry lookup table of a reflected (least significant bit
+/// first) CRC with the given reflected polynomial
+const fn reflected_table(poly: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+/// Generates the 256 entry lookup table of a non-reflected (most significant
+/// bit first) 16-bit CRC with the given polynomial
+const fn table16(poly: u16) -> [u16; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+const CRC16_CCITT_TABLE: [u16; 256] = table16(0x1021);
+const CRC16_IBM_TABLE: [u32; 256] = reflected_table(0xa001);
+const CRC32_TABLE: [u32; 256] = reflected_table(0xedb8_8320);
+
+/// Returns the CRC-16/CCITT-FALSE of `data` (polynomial 0x1021, initial value
+/// 0xFFFF, not reflected, no final XOR)
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(srec::digest::crc16_ccitt(b"123456789"), 0x29b1);
+/// ```
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    data.iter().fold(0xffff, |crc, &b| {
+        (crc << 8) ^ CRC16_CCITT_TABLE[usize::from((crc >> 8) as u8 ^ b)]
+    })
+}
+
+/// Returns the CRC-16/IBM (also known as CRC-16/ARC) of `data` (polynomial
+/// 0x8005, initial value 0, reflected, no final XOR)
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(srec::digest::crc16_ibm(b"123456789"), 0xbb3d);
+/// ```
+pub fn crc16_ibm(data: &[u8]) -> u16 {
+    data.iter().fold(0, |crc, &b| {
+        (crc >> 8) ^ CRC16_IBM_TABLE[usize::from(crc as u8 ^ b)] as u16
+    })
+}
+
+/// Returns the CRC-32 of `data`, as used by zlib and Ethernet (polynomial
+/// 0x04C11DB7, initial value 0xFFFFFFFF, reflected, final XOR 0xFFFFFFFF)
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(srec::digest::crc32(b"123456789"), 0xcbf4_3926);
+/// ```
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(0xffff_ffff, |crc, &b| {
+        (crc >> 8) ^ CRC32_TABLE[usize::from(crc as u8 ^ b)]
+    })
+}
+
+/// Returns the sum of the bytes of `data`, modulo 2^8
+pub fn sum8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &b| sum.wrapping_add(b))
+}
+
+/// Returns the sum of the bytes of `data`, modulo 2^16
+pub fn sum16(data: &[u8]) -> u16 {
+    data.iter()
+        .fold(0, |sum, &b| sum.wrapping_add(u16::from(b)))
+}
+
+/// Returns the sum of the bytes of `data`, modulo 2^32
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(srec::digest::sum32(&[0xff; 0x100]), 0xff00);
+/// assert_eq!(srec::digest::sum16(&[0xff; 0x102]), 0x00fe);
+/// ```
+pub fn sum32(data: &[u8]) -> u32 {
+    data.iter()
+        .fold(0, |sum, &b| sum.wrapping_add(u32::from(b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Check values from the catalogue of parametrised CRC algorithms
+    // https://reveng.sourceforge.io/crc-catalogue/
+    const CHECK: &[u8] = b"123456789";
+
+    #[test]
+    fn crc16_ccitt_returns_check_value() {
+        assert_eq!(crc16_ccitt(CHECK), 0x29b1);
+        assert_eq!(crc16_ccitt(&[]), 0xffff);
+    }
+
+    #[test]
+    fn crc16_ibm_returns_check_value() {
+        assert_eq!(crc16_ibm(CHECK), 0xbb3d);
+        assert_eq!(crc16_ibm(&[]), 0x0000);
+    }
+
+    #[test]
+    fn crc32_returns_check_value() {
+        assert_eq!(crc32(CHECK), 0xcbf4_3926);
+        assert_eq!(crc32(&[]), 0x0000_0000);
+    }
+
+    #[test]
+    fn sums_wrap_at_their_width() {
+        let data = [0xff; 0x10101];
+
+        assert_eq!(sum8(&data), 0xff);
+        assert_eq!(sum16(&data), 0xffff);
+        assert_eq!(sum32(&data), 0x00ff_ffff);
+    }
+}
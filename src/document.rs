@@ -0,0 +1,271 @@
+//! Editing of SREC files which preserves the formatting of every record left
+//! unchanged
+use crate::error::Error;
+use crate::reader::split_comment;
+use crate::record::Record;
+use crate::writer;
+use std::fmt;
+use std::ops::Range;
+
+/// A line of a [`Document`], holding its original text
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct Line {
+    /// Text of the line, including its line ending
+    text: String,
+    /// Range of `text` holding the record, without surrounding whitespace or
+    /// a trailing comment
+    span: Range<usize>,
+    /// Record as read from the line, or `None` for a blank line
+    original: Option<Record>,
+    /// Record as edited since the line was read
+    record: Option<Record>,
+}
+
+impl Line {
+    /// Appends the line to `out`, regenerating the record only if it was
+    /// changed
+    fn write_to(&self, out: &mut String) -> Result<(), writer::Error> {
+        let record = match &self.record {
+            Some(record) if self.record != self.original => record,
+            _ => {
+                out.push_str(&self.text);
+                return Ok(());
+            }
+        };
+
+        let original = &self.text[self.span.clone()];
+        let mut s = String::new();
+        record.encode_to(&mut s)?;
+
+        // Files are written in one case throughout, so the case of the tag
+        // is taken for the whole record
+        if original.starts_with('s') {
+            s.make_ascii_lowercase();
+        }
+
+        out.push_str(&self.text[..self.span.start]);
+        out.push_str(&s);
+        out.push_str(&self.text[self.span.end..]);
+        Ok(())
+    }
+}
+
+/// An SREC file held as records alongside the text they were read from, so
+/// editing some records regenerates only their lines
+///
+/// Lines whose records are left unchanged are written back byte for byte,
+/// keeping their case, whitespace and line endings, as are blank lines.
+/// Changed records are regenerated with the case, surrounding whitespace,
+/// trailing comment (see [`split_comment`]) and line ending of the line they
+/// were read from. [`encode`](Document::encode) gives the edited file.
+/// Displaying the document gives the same file, except that a line whose
+/// edited record cannot be encoded is written as it was read.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut document = srec::document::Document::parse(
+///     "s00600004844521b\r\nS107123400010203AC\r\n",
+/// )
+/// .unwrap();
+///
+/// if let Some(srec::Record::S1(data)) = document.get_mut(1) {
+///     data.data[0] = 0xff;
+/// }
+///
+/// assert_eq!(
+///     document.to_string(),
+///     "s00600004844521b\r\nS1071234FF010203AD\r\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct Document {
+    lines: Vec<Line>,
+}
+
+impl Document {
+    /// Reads a newline separated (either "\n" or "\r\n") string as a document
+    ///
+    /// Each line which is not blank must be a record, read as by
+    /// [`Record::from_str`](std::str::FromStr::from_str) after surrounding
+    /// whitespace is removed. The first line which cannot be read is returned
+    /// as an error with its line number.
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let mut lines = Vec::new();
+
+        for (i, text) in s.split_inclusive('\n').enumerate() {
+            let trimmed = text.trim_start();
+            let start = text.len() - trimmed.len();
+            let end = start + split_comment(trimmed).0.len();
+
+            let record = match &text[start..end] {
+                "" => None,
+                record => Some(
+                    record
+                        .parse::<Record>()
+                        .map_err(|error| Error::Syntax { line: i + 1, error })?,
+                ),
+            };
+
+            lines.push(Line {
+                text: text.into(),
+                span: start..end,
+                original: record.clone(),
+                record,
+            });
+        }
+
+        Ok(Document { lines })
+    }
+
+    /// Returns the number of records in the document
+    pub fn len(&self) -> usize {
+        self.records().count()
+    }
+
+    /// Returns true if the document has no records
+    pub fn is_empty(&self) -> bool {
+        self.records().next().is_none()
+    }
+
+    /// Returns the records of the document in order
+    pub fn records(&self) -> impl Iterator<Item = &Record> + '_ {
+        self.lines.iter().filter_map(|line| line.record.as_ref())
+    }
+
+    /// Returns the record at `index`, counting records rather than lines
+    pub fn get(&self, index: usize) -> Option<&Record> {
+        self.records().nth(index)
+    }
+
+    /// Returns the edited file, or the first error from
+    /// [`Record::encode_to`] if an edited record cannot be encoded
+    pub fn encode(&self) -> Result<String, writer::Error> {
+        let mut s = String::new();
+
+        for line in &self.lines {
+            line.write_to(&mut s)?;
+        }
+
+        Ok(s)
+    }
+
+    /// Returns the record at `index` for editing, counting records rather
+    /// than lines
+    ///
+    /// The record's line is regenerated only if the record is left different
+    /// from the one read.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Record> {
+        self.lines
+            .iter_mut()
+            .filter_map(|line| line.record.as_mut())
+            .nth(index)
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+
+        for line in &self.lines {
+            // write_to leaves `s` unchanged on error
+            if line.write_to(&mut s).is_err() {
+                s.push_str(&line.text);
+            }
+        }
+
+        f.write_str(&s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader;
+    use crate::record::*;
+
+    #[test]
+    fn document_unedited_returns_input_unchanged() {
+        let s = "S00600004844521B\r\n\n  s107123400010203ac \nS9031234B6";
+
+        let document = Document::parse(s).unwrap();
+
+        assert_eq!(document.len(), 3);
+        assert_eq!(document.to_string(), s);
+    }
+
+    #[test]
+    fn document_edited_record_keeps_line_formatting() {
+        let s = "S00600004844521B\n  s107123400010203ac ; entry\r\nS9031234B6";
+        let mut document = Document::parse(s).unwrap();
+
+        if let Some(record) = document.get_mut(2) {
+            *record = Record::S9(Address16(0x1238));
+        }
+        if let Some(Record::S1(data)) = document.get_mut(1) {
+            data.address = Address16(0x1238);
+        }
+
+        assert_eq!(
+            document.to_string(),
+            "S00600004844521B\n  s107123800010203a8 ; entry\r\nS9031238B2"
+        );
+    }
+
+    #[test]
+    fn document_edit_reverted_returns_original_line() {
+        let s = "s9031234b6\n";
+        let mut document = Document::parse(s).unwrap();
+
+        if let Some(Record::S9(address)) = document.get_mut(0) {
+            *address = Address16(0x1238);
+            *address = Address16(0x1234);
+        }
+
+        assert_eq!(document.to_string(), s);
+    }
+
+    #[test]
+    fn document_parse_invalid_record_returns_err_syntax_with_line() {
+        let s = "S00600004844521B\n\nS107123400010203FF\n";
+
+        let document = Document::parse(s);
+
+        assert!(matches!(
+            document,
+            Err(Error::Syntax {
+                line: 3,
+                error: reader::Error::ChecksumMismatch { .. }
+            })
+        ));
+    }
+
+    #[test]
+    fn document_encode_unencodable_edit_returns_err() {
+        let mut document = Document::parse("S9031234B6\n").unwrap();
+
+        if let Some(record) = document.get_mut(0) {
+            *record = Record::Unknown {
+                t: 10,
+                bytes: vec![],
+            };
+        }
+
+        assert_eq!(document.encode(), Err(writer::Error::InvalidRecordType));
+    }
+
+    #[test]
+    fn document_to_string_unencodable_edit_keeps_line_as_read() {
+        let s = "S00600004844521B\nS9031234B6\n";
+        let mut document = Document::parse(s).unwrap();
+
+        if let Some(record) = document.get_mut(1) {
+            *record = Record::Unknown {
+                t: 10,
+                bytes: vec![],
+            };
+        }
+
+        assert_eq!(document.to_string(), s);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::reader;
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Errors which may occur in the crate's higher-level APIs, which combine IO
+/// with reading and checking records
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Underlying reader or writer failed
+    Io(io::Error),
+    /// Line could not be read as a record
+    Syntax {
+        /// Line number, starting from 1
+        line: usize,
+        /// Reason the line could not be read
+        error: reader::Error,
+    },
+    /// Records were read but were not consistent with each other as a file
+    Semantic(reader::ValidationError),
+    /// Data record address was below the base address of a conversion
+    AddressBelowBase {
+        /// Line number, starting from 1
+        line: usize,
+        /// Start address of the data record
+        address: u32,
+    },
+    /// Data record would make a converted image larger than the configured
+    /// maximum
+    SpanTooLarge {
+        /// Line number, starting from 1
+        line: usize,
+        /// Size in bytes the image would have needed to be
+        span: u64,
+    },
+    /// Data record wrote to an address already written by an earlier record
+    Overlap {
+        /// Line number, starting from 1
+        line: usize,
+        /// First address written twice
+        address: u32,
+    },
+    /// Data record ran past the end of the 32-bit address space
+    AddressRangeOverflow {
+        /// Line number, starting from 1
+        line: usize,
+        /// Start address of the data record
+        address: u32,
+    },
+    /// Reading was cancelled through a cancellation flag
+    Cancelled,
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Syntax { error, .. } => Some(error),
+            Error::Semantic(err) => Some(err),
+            Error::AddressBelowBase { .. }
+            | Error::SpanTooLarge { .. }
+            | Error::Overlap { .. }
+            | Error::AddressRangeOverflow { .. }
+            | Error::Cancelled => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::Syntax { line, error } => write!(f, "line {}: {}", line, error),
+            Error::Semantic(err) => write!(f, "invalid file: {}", err),
+            Error::AddressBelowBase { line, address } => write!(
+                f,
+                "line {}: address {:#010X} below base address",
+                line, address
+            ),
+            Error::SpanTooLarge { line, span } => write!(
+                f,
+                "line {}: image span of {} bytes exceeds maximum",
+                line, span
+            ),
+            Error::Overlap { line, address } => write!(
+                f,
+                "line {}: data overlaps at address {:#010X}",
+                line, address
+            ),
+            Error::AddressRangeOverflow { line, address } => write!(
+                f,
+                "line {}: data at address {:#010X} runs past the end of the address space",
+                line, address
+            ),
+            Error::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<reader::ValidationError> for Error {
+    fn from(err: reader::ValidationError) -> Self {
+        Error::Semantic(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_syntax_includes_line_number() {
+        let err = Error::Syntax {
+            line: 3,
+            error: reader::Error::ChecksumMismatch {
+                t: 9,
+                address: Some(0x1234),
+                found: 0xb7,
+                computed: 0xb6,
+            },
+        };
+
+        let s = err.to_string();
+
+        assert_eq!(
+            s,
+            "line 3: checksum mismatch in S9 record at 0x1234: found 0xB7, computed 0xB6"
+        );
+    }
+
+    #[test]
+    fn source_syntax_returns_reader_error() {
+        let err = Error::Syntax {
+            line: 3,
+            error: reader::Error::ChecksumMismatch {
+                t: 9,
+                address: Some(0x1234),
+                found: 0xb7,
+                computed: 0xb6,
+            },
+        };
+
+        let source = error::Error::source(&err).map(|e| e.to_string());
+
+        assert_eq!(
+            source,
+            Some("checksum mismatch in S9 record at 0x1234: found 0xB7, computed 0xB6".into())
+        );
+    }
+
+    #[test]
+    fn from_io_error_returns_io() {
+        let err = Error::from(io::Error::other("oops"));
+
+        assert!(matches!(err, Error::Io(_)));
+    }
+}
@@ -0,0 +1,340 @@
+//! Generation of SREC files from maps of named fields, such as EEPROM
+//! configuration images
+use crate::flavor::{force_flavor, Flavor};
+use crate::record::*;
+use crate::writer::{self, generate_srec_file};
+use std::error;
+use std::fmt::{self, Write};
+
+/// Number of data bytes in each generated data record
+const RECORD_DATA_LEN: usize = 16;
+
+/// Byte order of a multi-byte field
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Endian {
+    /// Most significant byte at the lowest address
+    Big,
+    /// Least significant byte at the lowest address
+    Little,
+}
+
+/// A named value stored at a fixed address
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Field {
+    /// Name of the field, used in the textual map and errors
+    pub name: String,
+    /// Address of the field's first byte
+    pub address: u32,
+    /// Width of the field in bytes, from 1 to 8
+    pub width: usize,
+    /// Byte order of the field
+    pub endian: Endian,
+    /// Value of the field, which must fit in `width` bytes
+    pub value: u64,
+}
+
+impl Field {
+    /// Returns the bytes of the field in memory order
+    fn bytes(&self) -> Vec<u8> {
+        let be = self.value.to_be_bytes();
+        let mut bytes = be[be.len() - self.width..].to_vec();
+
+        if self.endian == Endian::Little {
+            bytes.reverse();
+        }
+
+        bytes
+    }
+
+    /// Returns the address one past the field's last byte
+    fn end(&self) -> u64 {
+        self.address as u64 + self.width as u64
+    }
+}
+
+/// Errors which may occur when generating records from a [`FieldMap`]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum Error {
+    /// Field width was not between 1 and 8 bytes
+    InvalidWidth(String),
+    /// Field value did not fit in the field's width
+    ValueTooWide(String),
+    /// Field extended beyond the end of the 32-bit address space
+    AddressOutOfRange(String),
+    /// Two fields shared at least one byte
+    Overlap(String, String),
+    /// Generated records could not be encoded
+    Write(writer::Error),
+}
+
+impl error::Error for Error {}
+
+impl From<writer::Error> for Error {
+    fn from(err: writer::Error) -> Self {
+        Error::Write(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidWidth(name) => write!(f, "field {}: width must be 1 to 8 bytes", name),
+            Error::ValueTooWide(name) => write!(f, "field {}: value too wide for field", name),
+            Error::AddressOutOfRange(name) => write!(f, "field {}: address out of range", name),
+            Error::Overlap(a, b) => write!(f, "fields {} and {} overlap", a, b),
+            Error::Write(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// A list of named fields which together make up a memory image, e.g. the
+/// configuration stored in an EEPROM
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::fieldmap::{Endian, FieldMap};
+///
+/// let mut map = FieldMap::new();
+/// map.add("magic", 0x0000, 2, Endian::Big, 0xa55a);
+/// map.add("baud_rate", 0x0002, 4, Endian::Little, 115_200);
+///
+/// assert_eq!(
+///     map.to_srec().unwrap(),
+///     "S1090000A55A00C2010034\nS9030000FC\n"
+/// );
+/// assert_eq!(
+///     map.to_map_string().unwrap(),
+///     "0x00000000  2  BE  0x000000000000A55A  magic\n\
+///      0x00000002  4  LE  0x000000000001C200  baud_rate\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct FieldMap {
+    /// Fields in the map, in the order they were added
+    pub fields: Vec<Field>,
+}
+
+impl FieldMap {
+    /// Creates an empty map
+    pub fn new() -> Self {
+        FieldMap::default()
+    }
+
+    /// Adds a field to the map, returning the map so calls can be chained
+    pub fn add(
+        &mut self,
+        name: impl Into<String>,
+        address: u32,
+        width: usize,
+        endian: Endian,
+        value: u64,
+    ) -> &mut Self {
+        self.fields.push(Field {
+            name: name.into(),
+            address,
+            width,
+            endian,
+            value,
+        });
+        self
+    }
+
+    /// Checks each field and returns them sorted by address
+    fn sorted_fields(&self) -> Result<Vec<&Field>, Error> {
+        for field in &self.fields {
+            if !(1..=8).contains(&field.width) {
+                return Err(Error::InvalidWidth(field.name.clone()));
+            }
+
+            if field.width < 8 && field.value >> (8 * field.width) != 0 {
+                return Err(Error::ValueTooWide(field.name.clone()));
+            }
+
+            if field.end() > 1 << 32 {
+                return Err(Error::AddressOutOfRange(field.name.clone()));
+            }
+        }
+
+        let mut fields = self.fields.iter().collect::<Vec<_>>();
+        fields.sort_by_key(|field| field.address);
+
+        for pair in fields.windows(2) {
+            if pair[0].end() > pair[1].address as u64 {
+                return Err(Error::Overlap(pair[0].name.clone(), pair[1].name.clone()));
+            }
+        }
+
+        Ok(fields)
+    }
+
+    /// Returns data records holding the value of every field
+    ///
+    /// Adjacent fields are merged into records of up to 16 data bytes. The
+    /// narrowest record type which can address every field is used.
+    pub fn to_records(&self) -> Result<Vec<Record>, Error> {
+        let fields = self.sorted_fields()?;
+        let mut runs: Vec<(u32, Vec<u8>)> = Vec::new();
+
+        for field in fields {
+            match runs.last_mut() {
+                Some((start, bytes))
+                    if *start as u64 + bytes.len() as u64 == field.address as u64 =>
+                {
+                    bytes.extend(field.bytes());
+                }
+                _ => runs.push((field.address, field.bytes())),
+            }
+        }
+
+        let mut records = Vec::new();
+        for (start, bytes) in runs {
+            for (i, chunk) in bytes.chunks(RECORD_DATA_LEN).enumerate() {
+                records.push(Record::S3(Data {
+                    address: Address32(start + (i * RECORD_DATA_LEN) as u32),
+                    data: chunk.to_vec(),
+                }));
+            }
+        }
+
+        for &flavor in &[Flavor::S19, Flavor::S28] {
+            let mut narrowed = records.clone();
+            if force_flavor(&mut narrowed, flavor).is_ok() {
+                return Ok(narrowed);
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Generates an SREC file holding the value of every field
+    ///
+    /// The file contains the records returned by
+    /// [`to_records`](Self::to_records) followed by a start address record of
+    /// the same width, with address 0.
+    pub fn to_srec(&self) -> Result<String, Error> {
+        let mut records = self.to_records()?;
+
+        records.push(match records.first() {
+            Some(Record::S2(_)) => Record::S8(Address24(0)),
+            Some(Record::S3(_)) => Record::S7(Address32(0)),
+            _ => Record::S9(Address16(0)),
+        });
+
+        Ok(generate_srec_file(&records)?)
+    }
+
+    /// Returns a textual map of the fields sorted by address, one per line,
+    /// giving the address, width, byte order, value and name of each
+    pub fn to_map_string(&self) -> Result<String, Error> {
+        let mut s = String::new();
+
+        for field in self.sorted_fields()? {
+            let _ = writeln!(
+                s,
+                "{:#010X}  {}  {}  {:#018X}  {}",
+                field.address,
+                field.width,
+                match field.endian {
+                    Endian::Big => "BE",
+                    Endian::Little => "LE",
+                },
+                field.value,
+                field.name
+            );
+        }
+
+        Ok(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_records_merges_adjacent_fields_and_splits_long_runs() {
+        let mut map = FieldMap::new();
+        map.add("a", 0x10, 8, Endian::Big, 0x0001_0203_0405_0607)
+            .add("b", 0x18, 8, Endian::Big, 0x0809_0a0b_0c0d_0e0f)
+            .add("c", 0x20, 1, Endian::Big, 0x10)
+            .add("d", 0x30, 2, Endian::Little, 0x1234);
+
+        let records = map.to_records().unwrap();
+
+        assert_eq!(
+            records,
+            [
+                Record::S1(Data {
+                    address: Address16(0x10),
+                    data: (0x00..0x10).collect(),
+                }),
+                Record::S1(Data {
+                    address: Address16(0x20),
+                    data: vec![0x10],
+                }),
+                Record::S1(Data {
+                    address: Address16(0x30),
+                    data: vec![0x34, 0x12],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn to_records_high_address_uses_wider_records() {
+        let mut map = FieldMap::new();
+        map.add("a", 0x0001_0000, 1, Endian::Big, 0xaa);
+
+        let records = map.to_records().unwrap();
+
+        assert_eq!(
+            records,
+            [Record::S2(Data {
+                address: Address24(0x0001_0000),
+                data: vec![0xaa],
+            })]
+        );
+    }
+
+    #[test]
+    fn to_records_value_too_wide_returns_err_value_too_wide() {
+        let mut map = FieldMap::new();
+        map.add("a", 0, 1, Endian::Big, 0x100);
+
+        let records = map.to_records();
+
+        assert_eq!(records, Err(Error::ValueTooWide("a".into())));
+    }
+
+    #[test]
+    fn to_records_invalid_width_returns_err_invalid_width() {
+        let mut map = FieldMap::new();
+        map.add("a", 0, 0, Endian::Big, 0);
+
+        let records = map.to_records();
+
+        assert_eq!(records, Err(Error::InvalidWidth("a".into())));
+    }
+
+    #[test]
+    fn to_records_overlapping_fields_returns_err_overlap() {
+        let mut map = FieldMap::new();
+        map.add("b", 0x11, 1, Endian::Big, 0)
+            .add("a", 0x10, 2, Endian::Big, 0);
+
+        let records = map.to_records();
+
+        assert_eq!(records, Err(Error::Overlap("a".into(), "b".into())));
+    }
+
+    #[test]
+    fn to_records_field_past_end_of_address_space_returns_err_address_out_of_range() {
+        let mut map = FieldMap::new();
+        map.add("a", 0xffff_fffe, 4, Endian::Big, 0);
+
+        let records = map.to_records();
+
+        assert_eq!(records, Err(Error::AddressOutOfRange("a".into())));
+    }
+}
@@ -0,0 +1,126 @@
+//! Reading of whole SREC files into their header, data and start address
+use crate::error::Error;
+use crate::image::Image;
+use crate::reader::{read_records_located, LocatedRecord};
+use crate::record::*;
+
+pub use crate::image::Segment;
+
+/// The contents of an SREC file: its header, the data it writes merged into
+/// contiguous segments, and its start address
+///
+/// # Examples
+///
+/// ```rust
+/// let file = srec::SrecFile::parse(
+///     "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(file.header(), Some("HDR"));
+/// assert_eq!(file.data_segments().len(), 1);
+/// assert_eq!(file.data_segments()[0].address, 0x1234);
+/// assert_eq!(file.data_segments()[0].data, [0, 1, 2, 3, 4, 5, 6, 7]);
+/// assert_eq!(file.start_address(), Some(0x1234));
+/// assert_eq!(file.record_count(), 4);
+/// ```
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct SrecFile {
+    header: Option<String>,
+    segments: Vec<Segment>,
+    start_address: Option<u32>,
+    record_count: usize,
+}
+
+impl SrecFile {
+    /// Reads a newline separated (either "\n" or "\r\n") string as an SREC
+    /// file
+    ///
+    /// Records are read as by [`read_records`](crate::reader::read_records),
+    /// and the first line which cannot be read is returned as an error with
+    /// its line number. Data records may appear in any order; later records
+    /// overwrite earlier ones where they overlap. If there is more than one
+    /// header or start address record, the last is used. Data running past the
+    /// end of the 32-bit address space is reported as
+    /// [`Error::AddressRangeOverflow`].
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let mut file = SrecFile::default();
+        let mut image = Image::new();
+
+        for LocatedRecord { line, record, .. } in read_records_located(s) {
+            let record = record.map_err(|error| Error::Syntax { line, error })?;
+            file.record_count += 1;
+
+            match record.memory_effect() {
+                MemoryEffect::Header(header) => file.header = Some(header.into()),
+                MemoryEffect::Write { address, data } => image
+                    .try_write(address, data)
+                    .map_err(|_| Error::AddressRangeOverflow { line, address })?,
+                MemoryEffect::StartAddress(address) => file.start_address = Some(address),
+                MemoryEffect::Count(_) | MemoryEffect::Unknown { .. } => {}
+            }
+        }
+
+        file.segments = image.to_segments();
+
+        Ok(file)
+    }
+
+    /// Returns the string of the header (S0) record, if the file has one
+    pub fn header(&self) -> Option<&str> {
+        self.header.as_deref()
+    }
+
+    /// Returns the data written by the file, merged into contiguous segments
+    /// in ascending address order
+    pub fn data_segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Returns the address given by the start address (S7, S8 or S9) record,
+    /// if the file has one
+    pub fn start_address(&self) -> Option<u32> {
+        self.start_address
+    }
+
+    /// Returns the number of records read from the file, of any type
+    pub fn record_count(&self) -> usize {
+        self.record_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader;
+
+    #[test]
+    fn srec_file_parse_invalid_record_returns_err_syntax_with_line() {
+        let s = "S00600004844521B\nS107123400010203FF\n";
+
+        let file = SrecFile::parse(s);
+
+        assert!(matches!(
+            file,
+            Err(Error::Syntax {
+                line: 2,
+                error: reader::Error::ChecksumMismatch { .. }
+            })
+        ));
+    }
+
+    #[test]
+    fn srec_file_parse_past_top_of_address_space_returns_err_address_range_overflow() {
+        let s = "S00600004844521B\nS307FFFFFFFF0001FB\n";
+
+        let file = SrecFile::parse(s);
+
+        assert!(matches!(
+            file,
+            Err(Error::AddressRangeOverflow {
+                line: 2,
+                address: 0xffff_ffff
+            })
+        ));
+    }
+}
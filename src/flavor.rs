@@ -0,0 +1,231 @@
+//! Conversion of files between the S19, S28 and S37 address widths
+use crate::record::*;
+use std::error;
+use std::fmt;
+use std::mem;
+
+/// Address width used uniformly by the data and start address records of a
+/// file, named after the record types used
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Flavor {
+    /// 16-bit addresses: S1 data and S9 start address records
+    S19,
+    /// 24-bit addresses: S2 data and S8 start address records
+    S28,
+    /// 32-bit addresses: S3 data and S7 start address records
+    S37,
+}
+
+impl Flavor {
+    /// Returns the highest address representable in this flavor
+    pub fn max_address(self) -> u32 {
+        match self {
+            Flavor::S19 => 0xffff,
+            Flavor::S28 => 0xff_ffff,
+            Flavor::S37 => 0xffff_ffff,
+        }
+    }
+}
+
+/// Error returned when a record's addresses cannot be represented in the
+/// requested flavor
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct AddressOutOfRange {
+    /// Index of the record in the input
+    pub index: usize,
+    /// Highest address the record uses - the address of its last data byte,
+    /// or its start address
+    pub address: u32,
+}
+
+impl error::Error for AddressOutOfRange {}
+
+impl fmt::Display for AddressOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "record {}: address {:#010X} out of range for flavor",
+            self.index, self.address
+        )
+    }
+}
+
+/// Returns the highest address used by a data or start address record
+fn highest_address(record: &Record) -> Option<u32> {
+    match record.memory_effect() {
+        MemoryEffect::Write { address, data } => {
+            Some(address.saturating_add((data.len() as u32).saturating_sub(1)))
+        }
+        MemoryEffect::StartAddress(address) => Some(address),
+        MemoryEffect::Header(_) | MemoryEffect::Count(_) | MemoryEffect::Unknown { .. } => None,
+    }
+}
+
+/// Returns a data or start address record converted to `flavor`, leaving
+/// other records unchanged
+///
+/// The address must already have been checked to fit.
+fn convert(record: Record, flavor: Flavor) -> Record {
+    let (address, data): (u32, _) = match record {
+        Record::S1(Data { address, data }) => (address.into(), Some(data)),
+        Record::S2(Data { address, data }) => (address.into(), Some(data)),
+        Record::S3(Data { address, data }) => (address.into(), Some(data)),
+        Record::S7(address) => (address.into(), None),
+        Record::S8(address) => (address.into(), None),
+        Record::S9(address) => (address.into(), None),
+        record => return record,
+    };
+
+    match (flavor, data) {
+        (Flavor::S19, Some(data)) => Record::S1(Data {
+            address: Address16(address as u16),
+            data,
+        }),
+        (Flavor::S28, Some(data)) => Record::S2(Data {
+            address: Address24(address),
+            data,
+        }),
+        (Flavor::S37, Some(data)) => Record::S3(Data {
+            address: Address32(address),
+            data,
+        }),
+        (Flavor::S19, None) => Record::S9(Address16(address as u16)),
+        (Flavor::S28, None) => Record::S8(Address24(address)),
+        (Flavor::S37, None) => Record::S7(Address32(address)),
+    }
+}
+
+/// Converts all data and start address records to the record types of
+/// `flavor`, for loaders which accept only one flavor
+///
+/// Header and count records are left unchanged. The records are only modified
+/// if every data and start address record fits in the flavor's address width.
+/// Otherwise the first record which does not fit is returned as an error and
+/// the records are left untouched.
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::flavor::{force_flavor, Flavor};
+/// use srec::{Address16, Address32, Data, Record};
+///
+/// let mut records = vec![
+///     Record::S1(Data {
+///         address: Address16(0x1234),
+///         data: vec![0x00, 0x01],
+///     }),
+///     Record::S9(Address16(0x1234)),
+/// ];
+///
+/// force_flavor(&mut records, Flavor::S37).unwrap();
+///
+/// assert_eq!(
+///     records,
+///     [
+///         Record::S3(Data {
+///             address: Address32(0x1234),
+///             data: vec![0x00, 0x01],
+///         }),
+///         Record::S7(Address32(0x1234)),
+///     ]
+/// );
+/// ```
+pub fn force_flavor(records: &mut [Record], flavor: Flavor) -> Result<(), AddressOutOfRange> {
+    for (index, record) in records.iter().enumerate() {
+        if let Some(address) = highest_address(record) {
+            if address > flavor.max_address() {
+                return Err(AddressOutOfRange { index, address });
+            }
+        }
+    }
+
+    for record in records.iter_mut() {
+        *record = convert(mem::replace(record, Record::S0(String::new())), flavor);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn force_flavor_s19_narrows_wide_records() {
+        let mut records = vec![
+            Record::S0("HDR".into()),
+            Record::S3(Data {
+                address: Address32(0x1234),
+                data: vec![0xaa],
+            }),
+            Record::S5(Count16(1)),
+            Record::S8(Address24(0x1234)),
+        ];
+
+        let r = force_flavor(&mut records, Flavor::S19);
+
+        assert_eq!(r, Ok(()));
+        assert_eq!(
+            records,
+            [
+                Record::S0("HDR".into()),
+                Record::S1(Data {
+                    address: Address16(0x1234),
+                    data: vec![0xaa],
+                }),
+                Record::S5(Count16(1)),
+                Record::S9(Address16(0x1234)),
+            ]
+        );
+    }
+
+    #[test]
+    fn force_flavor_data_ending_out_of_range_returns_err_and_leaves_records() {
+        let mut records = vec![
+            Record::S9(Address16(0x1234)),
+            Record::S2(Data {
+                address: Address24(0xfffe),
+                data: vec![0x00, 0x01, 0x02],
+            }),
+        ];
+        let original = records.clone();
+
+        let r = force_flavor(&mut records, Flavor::S19);
+
+        assert_eq!(
+            r,
+            Err(AddressOutOfRange {
+                index: 1,
+                address: 0x10000
+            })
+        );
+        assert_eq!(records, original);
+    }
+
+    #[test]
+    fn force_flavor_start_address_out_of_range_returns_err() {
+        let mut records = vec![Record::S7(Address32(0x0100_0000))];
+
+        let r = force_flavor(&mut records, Flavor::S28);
+
+        assert_eq!(
+            r,
+            Err(AddressOutOfRange {
+                index: 0,
+                address: 0x0100_0000
+            })
+        );
+    }
+
+    #[test]
+    fn force_flavor_s37_accepts_top_of_address_space() {
+        let mut records = vec![Record::S3(Data {
+            address: Address32(0xffff_ffff),
+            data: vec![0xaa],
+        })];
+
+        let r = force_flavor(&mut records, Flavor::S37);
+
+        assert_eq!(r, Ok(()));
+    }
+}
@@ -0,0 +1,423 @@
+//! Interpretation of the identification conventions commonly used in S0
+//! header strings
+use std::fmt;
+
+/// Firmware identification fields found in an S0 header string
+///
+/// Toolchains and release scripts embed identification in the S0 header in a
+/// variety of ways, e.g. `"blink v1.2 r3 2020-01-31"` or
+/// `"name=blink ver=1.2 rev=3 date=2020-01-31"`. [`HeaderInfo::parse`]
+/// recognises the most common of these, and the [`Display`](fmt::Display)
+/// implementation writes the fields back out in a form `parse` reads.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct HeaderInfo {
+    /// Module or product name
+    pub name: Option<String>,
+    /// Version, without any leading "v"
+    pub version: Option<String>,
+    /// Revision, without any leading "r"
+    pub revision: Option<String>,
+    /// Date, as written in the header
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Field {
+    Name,
+    Version,
+    Revision,
+    Date,
+}
+
+impl Field {
+    fn from_key(key: &str) -> Option<Field> {
+        match key.to_ascii_lowercase().as_str() {
+            "name" | "mname" | "mod" | "module" => Some(Field::Name),
+            "v" | "ver" | "version" => Some(Field::Version),
+            "r" | "rev" | "revision" => Some(Field::Revision),
+            "date" => Some(Field::Date),
+            _ => None,
+        }
+    }
+}
+
+/// Splits a token such as "v1.2" or "rev3" into its field and value, where
+/// the value must start with a digit
+fn split_prefixed(token: &str) -> Option<(Field, &str)> {
+    let split = token.find(|c: char| c.is_ascii_digit())?;
+    let (prefix, value) = token.split_at(split);
+
+    match Field::from_key(prefix) {
+        Some(field @ Field::Version) | Some(field @ Field::Revision) => Some((field, value)),
+        _ => None,
+    }
+}
+
+/// Returns true for dates written as YYYYMMDD or three numeric groups
+/// separated by '-', '/' or '.' with a four digit year at either end
+fn is_date(token: &str) -> bool {
+    let groups = token.split(['-', '/', '.']).collect::<Vec<_>>();
+
+    if groups
+        .iter()
+        .any(|g| g.is_empty() || !g.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return false;
+    }
+
+    match groups.as_slice() {
+        [ymd] => ymd.len() == 8 && (ymd.starts_with("19") || ymd.starts_with("20")),
+        [a, b, c] => {
+            let short = |g: &&str| g.len() <= 2;
+            (a.len() == 4 && short(b) && short(c)) || (short(a) && short(b) && c.len() == 4)
+        }
+        _ => false,
+    }
+}
+
+impl HeaderInfo {
+    /// Extracts identification fields from an S0 header string
+    ///
+    /// Parsing is best-effort and never fails. Tokens are separated by
+    /// whitespace, ',' or ';' and are recognised as:
+    ///
+    /// - `key=value` or `key:value`, where key is one of `name`, `mname`,
+    ///   `mod`, `module`, `ver`, `version`, `rev`, `revision` or `date`
+    /// - a key followed by its value as the next token, e.g. `ver 1.2`
+    /// - a version or revision prefix directly followed by a number, e.g.
+    ///   `v1.2`, `ver2`, `r3` or `rev4`
+    /// - a date, either `YYYYMMDD` or three numeric groups separated by '-',
+    ///   '/' or '.' with a four digit year first or last
+    ///
+    /// Remaining tokens are joined with single spaces to form the name.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let info = srec::HeaderInfo::parse("blink v1.2 r3 2020-01-31");
+    ///
+    /// assert_eq!(info.name.as_deref(), Some("blink"));
+    /// assert_eq!(info.version.as_deref(), Some("1.2"));
+    /// assert_eq!(info.revision.as_deref(), Some("3"));
+    /// assert_eq!(info.date.as_deref(), Some("2020-01-31"));
+    /// ```
+    pub fn parse(s0: &str) -> HeaderInfo {
+        let mut info = HeaderInfo::default();
+        let mut name_words = Vec::new();
+
+        let mut tokens = s0
+            .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+            .filter(|token| !token.is_empty());
+
+        while let Some(token) = tokens.next() {
+            let (field, value) = if let Some((key, value)) = token.split_once(['=', ':']) {
+                match Field::from_key(key) {
+                    Some(field) => (field, value.to_string()),
+                    None if is_date(token) => (Field::Date, token.to_string()),
+                    None => (Field::Name, token.to_string()),
+                }
+            } else if let Some((field, value)) = split_prefixed(token) {
+                (field, value.to_string())
+            } else if is_date(token) {
+                (Field::Date, token.to_string())
+            } else {
+                match Field::from_key(token) {
+                    Some(field) if field != Field::Name || info.name.is_none() => {
+                        match tokens.next() {
+                            Some(value) => (field, value.to_string()),
+                            None => (Field::Name, token.to_string()),
+                        }
+                    }
+                    _ => (Field::Name, token.to_string()),
+                }
+            };
+
+            match field {
+                Field::Name => name_words.push(value),
+                Field::Version => info.version = Some(value),
+                Field::Revision => info.revision = Some(value),
+                Field::Date => info.date = Some(value),
+            }
+        }
+
+        if !name_words.is_empty() {
+            info.name = Some(name_words.join(" "));
+        }
+
+        info
+    }
+}
+
+impl fmt::Display for HeaderInfo {
+    /// Formats the fields as `name ver=... rev=... date=...`, omitting any
+    /// which are not set
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = [
+            self.name.as_ref().map(|name| name.to_string()),
+            self.version
+                .as_ref()
+                .map(|version| format!("ver={}", version)),
+            self.revision
+                .as_ref()
+                .map(|revision| format!("rev={}", revision)),
+            self.date.as_ref().map(|date| format!("date={}", date)),
+        ];
+
+        write!(
+            f,
+            "{}",
+            fields
+                .iter()
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" ")
+        )
+    }
+}
+
+/// The fixed-width fields of an S0 header as laid out in Motorola's
+/// specification of the format
+///
+/// The specification divides the header's payload into a module name of 20
+/// bytes, a version of 2 bytes, a revision of 2 bytes and a description of up
+/// to 36 bytes, each padded with spaces or NULs. Many tools write a free-form
+/// string instead, for which [`HeaderInfo`] is better suited.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct MotorolaHeader {
+    /// Module name
+    pub mname: Option<String>,
+    /// Version number
+    pub ver: Option<String>,
+    /// Revision number
+    pub rev: Option<String>,
+    /// Text comment
+    pub description: Option<String>,
+}
+
+/// Widths in bytes of the fixed-width header fields, in order
+const MOTOROLA_WIDTHS: [usize; 4] = [20, 2, 2, 36];
+
+impl MotorolaHeader {
+    /// Extracts the fixed-width fields from the payload of an S0 header
+    ///
+    /// Parsing is best-effort and never fails. Each field has its padding
+    /// (spaces and NULs) removed, and is `None` if that leaves it empty or the
+    /// payload ends before it. Bytes which are not valid UTF-8 are replaced
+    /// with U+FFFD, and bytes beyond the description are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let header = srec::header::MotorolaHeader::parse(b"BLINK               0102Blinks an LED");
+    ///
+    /// assert_eq!(header.mname.as_deref(), Some("BLINK"));
+    /// assert_eq!(header.ver.as_deref(), Some("01"));
+    /// assert_eq!(header.rev.as_deref(), Some("02"));
+    /// assert_eq!(header.description.as_deref(), Some("Blinks an LED"));
+    /// ```
+    pub fn parse(s0: &[u8]) -> MotorolaHeader {
+        let mut rest = s0;
+        let mut fields = MOTOROLA_WIDTHS.iter().map(|&width| {
+            let (field, tail) = rest.split_at(width.min(rest.len()));
+            rest = tail;
+
+            let field = String::from_utf8_lossy(field);
+            let field = field.trim_matches(|c| c == ' ' || c == '\0');
+            Some(field.to_string()).filter(|field| !field.is_empty())
+        });
+
+        MotorolaHeader {
+            mname: fields.next().flatten(),
+            ver: fields.next().flatten(),
+            rev: fields.next().flatten(),
+            description: fields.next().flatten(),
+        }
+    }
+}
+
+impl fmt::Display for MotorolaHeader {
+    /// Formats the fields laid out as the specification requires, padding
+    /// each with spaces and truncating any too long to fit, with the
+    /// description left unpadded
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = [&self.mname, &self.ver, &self.rev, &self.description];
+
+        for (i, (field, &width)) in fields.iter().zip(&MOTOROLA_WIDTHS).enumerate() {
+            let field = field.as_deref().unwrap_or("");
+            let end = (0..=width.min(field.len()))
+                .rev()
+                .find(|&end| field.is_char_boundary(end))
+                .unwrap_or(0);
+
+            if i == fields.len() - 1 {
+                write!(f, "{}", &field[..end])?;
+            } else {
+                write!(f, "{:<width$}", &field[..end], width = width)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_empty_string_returns_no_fields() {
+        let info = HeaderInfo::parse("");
+
+        assert_eq!(info, HeaderInfo::default());
+    }
+
+    #[test]
+    fn parse_name_only_returns_name() {
+        let info = HeaderInfo::parse("blink.mot");
+
+        assert_eq!(info.name.as_deref(), Some("blink.mot"));
+        assert_eq!(info.version, None);
+        assert_eq!(info.revision, None);
+        assert_eq!(info.date, None);
+    }
+
+    #[test]
+    fn parse_prefixed_tokens_returns_all_fields() {
+        let info = HeaderInfo::parse("blink v1.2 r3 2020-01-31");
+
+        assert_eq!(
+            info,
+            HeaderInfo {
+                name: Some("blink".into()),
+                version: Some("1.2".into()),
+                revision: Some("3".into()),
+                date: Some("2020-01-31".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_key_value_pairs_returns_all_fields() {
+        let info = HeaderInfo::parse("NAME=blink, VER:1.2; rev=B date=31/01/2020");
+
+        assert_eq!(
+            info,
+            HeaderInfo {
+                name: Some("blink".into()),
+                version: Some("1.2".into()),
+                revision: Some("B".into()),
+                date: Some("31/01/2020".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_key_followed_by_value_returns_field() {
+        let info = HeaderInfo::parse("bootloader version 2.0 rev 7");
+
+        assert_eq!(info.name.as_deref(), Some("bootloader"));
+        assert_eq!(info.version.as_deref(), Some("2.0"));
+        assert_eq!(info.revision.as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn parse_compact_date_returns_date() {
+        let info = HeaderInfo::parse("app 20200131");
+
+        assert_eq!(info.name.as_deref(), Some("app"));
+        assert_eq!(info.date.as_deref(), Some("20200131"));
+    }
+
+    #[test]
+    fn parse_multiple_unrecognised_words_joins_name() {
+        let info = HeaderInfo::parse("motor controller v3");
+
+        assert_eq!(info.name.as_deref(), Some("motor controller"));
+        assert_eq!(info.version.as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn parse_dotted_number_without_prefix_is_not_a_date() {
+        let info = HeaderInfo::parse("1.2.3");
+
+        assert_eq!(info.name.as_deref(), Some("1.2.3"));
+        assert_eq!(info.date, None);
+    }
+
+    #[test]
+    fn display_writes_all_fields() {
+        let info = HeaderInfo {
+            name: Some("blink".into()),
+            version: Some("1.2".into()),
+            revision: Some("3".into()),
+            date: Some("2020-01-31".into()),
+        };
+
+        let s = info.to_string();
+
+        assert_eq!(s, "blink ver=1.2 rev=3 date=2020-01-31");
+    }
+
+    #[test]
+    fn display_omits_missing_fields() {
+        let info = HeaderInfo {
+            version: Some("1.2".into()),
+            ..HeaderInfo::default()
+        };
+
+        let s = info.to_string();
+
+        assert_eq!(s, "ver=1.2");
+    }
+
+    #[test]
+    fn display_then_parse_returns_same_fields() {
+        let info = HeaderInfo {
+            name: Some("motor controller".into()),
+            version: Some("1.2".into()),
+            revision: Some("B".into()),
+            date: Some("2020-01-31".into()),
+        };
+
+        let info2 = HeaderInfo::parse(&info.to_string());
+
+        assert_eq!(info, info2);
+    }
+
+    #[test]
+    fn motorola_header_parse_short_payload_returns_present_fields() {
+        let header = MotorolaHeader::parse(b"BOOT\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0 1");
+
+        assert_eq!(
+            header,
+            MotorolaHeader {
+                mname: Some("BOOT".into()),
+                ver: Some("1".into()),
+                ..MotorolaHeader::default()
+            }
+        );
+    }
+
+    #[test]
+    fn motorola_header_display_then_parse_returns_same_fields() {
+        let header = MotorolaHeader {
+            mname: Some("A module name longer than the field".into()),
+            rev: Some("3".into()),
+            description: Some("release build".into()),
+            ..MotorolaHeader::default()
+        };
+
+        let s = header.to_string();
+        let header2 = MotorolaHeader::parse(s.as_bytes());
+
+        assert_eq!(s, "A module name longer  3 release build");
+        assert_eq!(
+            header2,
+            MotorolaHeader {
+                mname: Some("A module name longer".into()),
+                ..header
+            }
+        );
+    }
+}
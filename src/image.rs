@@ -0,0 +1,609 @@
+//! Sparse memory images built from the data of SREC files
+use crate::record::{MemoryEffect, Record};
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+
+/// A contiguous run of data bytes
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct Segment {
+    /// Address of the first byte
+    pub address: u32,
+    /// Data bytes
+    pub data: Vec<u8>,
+}
+
+impl Segment {
+    /// Returns the address one past the segment's last byte
+    pub fn end(&self) -> u64 {
+        self.address as u64 + self.data.len() as u64
+    }
+}
+
+/// Error returned by [`Image::try_write`] for data running past the end of the
+/// 32-bit address space
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct AddressRangeOverflow {
+    /// Address of the first byte of the data
+    pub address: u32,
+    /// Length of the data
+    pub len: usize,
+}
+
+impl error::Error for AddressRangeOverflow {}
+
+impl fmt::Display for AddressRangeOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes at {:#010X} run past the end of the address space",
+            self.len, self.address
+        )
+    }
+}
+
+/// A range of bytes filled in by [`Image::expand_to_alignment`]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Padding {
+    /// Address of the first filled byte
+    pub address: u32,
+    /// Number of bytes filled
+    pub len: usize,
+}
+
+/// A sparse memory image: the bytes at each written address, held as blocks
+/// of contiguous data
+///
+/// Blocks never overlap or touch - writing next to or over a block extends
+/// it, merging blocks it bridges.
+///
+/// # Examples
+///
+/// ```rust
+/// let image = srec::Image::default()
+///     .with_data(0x1234, &[0x00, 0x01])
+///     .with_data(0x1236, &[0x02])
+///     .with_data(0x2000, &[0xff]);
+///
+/// assert_eq!(
+///     image.blocks().collect::<Vec<_>>(),
+///     [(0x1234, &[0x00, 0x01, 0x02][..]), (0x2000, &[0xff][..])]
+/// );
+/// assert_eq!(image.get(0x1235), Some(0x01));
+/// assert_eq!(image.get(0x1237), None);
+/// ```
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct Image {
+    blocks: BTreeMap<u32, Vec<u8>>,
+}
+
+impl Image {
+    /// Creates an empty image
+    pub fn new() -> Self {
+        Image::default()
+    }
+
+    /// Returns true if no bytes have been written
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Returns the number of bytes written
+    pub fn len(&self) -> u64 {
+        self.blocks.values().map(|block| block.len() as u64).sum()
+    }
+
+    /// Returns the blocks of contiguous data in ascending address order, as
+    /// their start address and bytes
+    pub fn blocks(&self) -> impl Iterator<Item = (u32, &[u8])> + '_ {
+        self.blocks
+            .iter()
+            .map(|(&address, block)| (address, &block[..]))
+    }
+
+    /// Returns the blocks of contiguous data in ascending address order as
+    /// owned segments
+    pub fn to_segments(&self) -> Vec<Segment> {
+        self.blocks()
+            .map(|(address, data)| Segment {
+                address,
+                data: data.to_vec(),
+            })
+            .collect()
+    }
+
+    /// Returns the byte at `address`, if it has been written
+    pub fn get(&self, address: u32) -> Option<u8> {
+        let (&start, block) = self.blocks.range(..=address).next_back()?;
+        block.get((address - start) as usize).copied()
+    }
+
+    /// Returns the start addresses of the blocks which overlap or touch the
+    /// range `start..end`, highest first
+    fn touching(&self, start: u64, end: u64) -> Vec<u32> {
+        self.blocks
+            .range(..=end.min(u32::MAX as u64) as u32)
+            .rev()
+            .take_while(|(&address, block)| address as u64 + block.len() as u64 >= start)
+            .map(|(&address, _)| address)
+            .collect()
+    }
+
+    /// Returns the ranges within `address..address + len` which have not been
+    /// written, in ascending order, stopping at the end of the address space
+    pub(crate) fn gaps(&self, address: u32, len: usize) -> Vec<(u32, usize)> {
+        let end = (address as u64 + len as u64).min(1 << 32);
+        let mut gaps = Vec::new();
+        let mut at = address as u64;
+
+        for start in self.touching(address as u64, end).into_iter().rev() {
+            let block_end = start as u64 + self.blocks[&start].len() as u64;
+            if (start as u64) > at {
+                gaps.push((at as u32, (start as u64 - at) as usize));
+            }
+            at = at.max(block_end);
+        }
+
+        if at < end {
+            gaps.push((at as u32, (end - at) as usize));
+        }
+
+        gaps
+    }
+
+    /// Writes `data` at `address`, overwriting any bytes already written there
+    ///
+    /// Data running past the end of the 32-bit address space is truncated; use
+    /// [`try_write`](Self::try_write) to report it instead.
+    pub fn write(&mut self, address: u32, data: &[u8]) {
+        let room = (1u64 << 32) - address as u64;
+        let data = &data[..(data.len() as u64).min(room) as usize];
+        if data.is_empty() {
+            return;
+        }
+
+        let end = address as u64 + data.len() as u64;
+        let touching = self.touching(address as u64, end);
+
+        // Usual case of data directly following or inside the previous block:
+        // extend the block in place rather than rebuilding it
+        if let [start] = touching[..] {
+            if start <= address {
+                if let Some(block) = self.blocks.get_mut(&start) {
+                    let offset = (address - start) as usize;
+                    if block.len() < offset + data.len() {
+                        block.resize(offset + data.len(), 0);
+                    }
+                    block[offset..offset + data.len()].copy_from_slice(data);
+                    return;
+                }
+            }
+        }
+
+        let merged_start = touching.last().map_or(address, |&start| start.min(address));
+        let mut merged = Vec::new();
+
+        for start in touching.into_iter().rev() {
+            if let Some(block) = self.blocks.remove(&start) {
+                let offset = (start - merged_start) as usize;
+                if merged.len() < offset + block.len() {
+                    merged.resize(offset + block.len(), 0);
+                }
+                merged[offset..offset + block.len()].copy_from_slice(&block);
+            }
+        }
+
+        let offset = (address - merged_start) as usize;
+        if merged.len() < offset + data.len() {
+            merged.resize(offset + data.len(), 0);
+        }
+        merged[offset..offset + data.len()].copy_from_slice(data);
+
+        self.blocks.insert(merged_start, merged);
+    }
+
+    /// Grows each block to start and end on a multiple of `align` bytes,
+    /// filling the bytes added with `fill`, and returns the ranges filled in
+    /// ascending address order
+    ///
+    /// Needed for devices which can only program whole aligned words. Blocks
+    /// which grow to touch are merged; bytes already written are never
+    /// changed. An `align` of 0 or 1 leaves the image unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use srec::image::{Image, Padding};
+    ///
+    /// let mut image = Image::new().with_data(0x1235, &[0x01, 0x02]);
+    ///
+    /// let padding = image.expand_to_alignment(4, 0xff);
+    ///
+    /// assert_eq!(
+    ///     image.blocks().collect::<Vec<_>>(),
+    ///     [(0x1234, &[0xff, 0x01, 0x02, 0xff][..])]
+    /// );
+    /// assert_eq!(
+    ///     padding,
+    ///     [
+    ///         Padding { address: 0x1234, len: 1 },
+    ///         Padding { address: 0x1237, len: 1 },
+    ///     ]
+    /// );
+    /// ```
+    pub fn expand_to_alignment(&mut self, align: u32, fill: u8) -> Vec<Padding> {
+        if align <= 1 {
+            return Vec::new();
+        }
+
+        let align = align as u64;
+        let ranges = self
+            .blocks()
+            .map(|(address, block)| {
+                let start = address as u64 / align * align;
+                let end = (address as u64 + block.len() as u64).div_ceil(align) * align;
+                (start as u32, (end.min(1 << 32) - start) as usize)
+            })
+            .collect::<Vec<_>>();
+
+        let mut padding = Vec::new();
+        for (address, len) in ranges {
+            for (address, len) in self.gaps(address, len) {
+                self.write(address, &vec![fill; len]);
+                padding.push(Padding { address, len });
+            }
+        }
+
+        padding
+    }
+
+    /// Returns the bytes of this image which differ from, or are absent in,
+    /// `other`
+    ///
+    /// Useful for producing a minimal delta to program over a device already
+    /// holding `other`, e.g. when most of a firmware is unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let new = srec::Image::new().with_data(0x1234, &[0x00, 0x01, 0x02, 0x03]);
+    /// let old = srec::Image::new().with_data(0x1234, &[0x00, 0xff, 0x02]);
+    ///
+    /// let delta = new.subtract(&old);
+    ///
+    /// assert_eq!(
+    ///     delta.blocks().collect::<Vec<_>>(),
+    ///     [(0x1235, &[0x01][..]), (0x1237, &[0x03][..])]
+    /// );
+    /// ```
+    pub fn subtract(&self, other: &Image) -> Image {
+        let mut delta = Image::new();
+
+        for (address, block) in self.blocks() {
+            let mut run: Option<usize> = None;
+
+            for (i, &b) in block.iter().enumerate() {
+                let differs = other.get(address + i as u32) != Some(b);
+
+                match (run, differs) {
+                    (None, true) => run = Some(i),
+                    (Some(start), false) => {
+                        delta.write(address + start as u32, &block[start..i]);
+                        run = None;
+                    }
+                    _ => {}
+                }
+            }
+
+            if let Some(start) = run {
+                delta.write(address + start as u32, &block[start..]);
+            }
+        }
+
+        delta
+    }
+
+    /// Writes `data` at `address` as [`write`](Self::write), or returns an
+    /// error without writing anything if it runs past the end of the 32-bit
+    /// address space
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut image = srec::Image::new();
+    ///
+    /// assert!(image.try_write(0xffff_fffe, &[0x00, 0x01]).is_ok());
+    /// assert!(image.try_write(0xffff_fffe, &[0x00, 0x01, 0x02]).is_err());
+    /// assert_eq!(image.len(), 2);
+    /// ```
+    pub fn try_write(&mut self, address: u32, data: &[u8]) -> Result<(), AddressRangeOverflow> {
+        if address as u64 + data.len() as u64 > 1 << 32 {
+            return Err(AddressRangeOverflow {
+                address,
+                len: data.len(),
+            });
+        }
+
+        self.write(address, data);
+        Ok(())
+    }
+
+    /// Writes `data` at `address` as [`write`](Self::write), returning the
+    /// image so calls can be chained
+    pub fn with_data(mut self, address: u32, data: &[u8]) -> Self {
+        self.write(address, data);
+        self
+    }
+}
+
+/// Iterator adapter merging consecutive data records with contiguous
+/// addresses into segments, returned by [`coalesce`]
+#[derive(Debug, Clone)]
+pub struct Coalesce<I> {
+    records: I,
+    pending: Option<Segment>,
+}
+
+impl<I: Iterator<Item = Record>> Iterator for Coalesce<I> {
+    type Item = Segment;
+
+    fn next(&mut self) -> Option<Segment> {
+        for record in &mut self.records {
+            let (address, data) = match record.memory_effect() {
+                MemoryEffect::Write { address, data } if !data.is_empty() => (address, data),
+                _ => continue,
+            };
+
+            match &mut self.pending {
+                Some(segment) if segment.end() == address as u64 => {
+                    segment.data.extend_from_slice(data);
+                }
+                pending => {
+                    let segment = pending.replace(Segment {
+                        address,
+                        data: data.to_vec(),
+                    });
+                    if segment.is_some() {
+                        return segment;
+                    }
+                }
+            }
+        }
+
+        self.pending.take()
+    }
+}
+
+/// Merges consecutive data records whose addresses are contiguous into
+/// segments as they are read, without building an [`Image`]
+///
+/// Unlike an image, records are taken in file order: a record which does not
+/// start where the previous one ended begins a new segment, even if it
+/// overlaps or precedes data already returned. Records other than data
+/// records, and data records with no data, are skipped without ending a
+/// segment.
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::{Address16, Data, Record};
+///
+/// let records = [
+///     Record::S0("HDR".into()),
+///     Record::S1(Data {
+///         address: Address16(0x1234),
+///         data: vec![0x00, 0x01, 0x02, 0x03],
+///     }),
+///     Record::S1(Data {
+///         address: Address16(0x1238),
+///         data: vec![0x04, 0x05, 0x06, 0x07],
+///     }),
+///     Record::S1(Data {
+///         address: Address16(0x1000),
+///         data: vec![0xaa, 0xbb],
+///     }),
+///     Record::S9(Address16(0x1234)),
+/// ];
+///
+/// let segments = srec::image::coalesce(records).collect::<Vec<_>>();
+///
+/// assert_eq!(segments.len(), 2);
+/// assert_eq!(segments[0].address, 0x1234);
+/// assert_eq!(segments[0].data, [0, 1, 2, 3, 4, 5, 6, 7]);
+/// assert_eq!(segments[1].address, 0x1000);
+/// assert_eq!(segments[1].data, [0xaa, 0xbb]);
+/// ```
+pub fn coalesce<I: IntoIterator<Item = Record>>(records: I) -> Coalesce<I::IntoIter> {
+    Coalesce {
+        records: records.into_iter(),
+        pending: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::*;
+
+    fn blocks(writes: &[(u32, &[u8])]) -> Vec<(u32, Vec<u8>)> {
+        let mut image = Image::new();
+        for &(address, data) in writes {
+            image.write(address, data);
+        }
+        image.blocks.into_iter().collect()
+    }
+
+    #[test]
+    fn write_touching_writes_merge() {
+        let b = blocks(&[
+            (0x10, &[0x00, 0x01]),
+            (0x12, &[0x02]),
+            (0x0e, &[0xfe, 0xff]),
+        ]);
+
+        assert_eq!(b, [(0x0e, vec![0xfe, 0xff, 0x00, 0x01, 0x02])]);
+    }
+
+    #[test]
+    fn write_overlapping_write_bridges_blocks_and_overwrites() {
+        let b = blocks(&[
+            (0x10, &[0x00]),
+            (0x14, &[0x04, 0x05]),
+            (0x11, &[0xa1, 0xa2, 0xa3, 0xa4]),
+        ]);
+
+        assert_eq!(b, [(0x10, vec![0x00, 0xa1, 0xa2, 0xa3, 0xa4, 0x05])]);
+    }
+
+    #[test]
+    fn write_gap_keeps_blocks_apart() {
+        let b = blocks(&[(0x20, &[0x20]), (0x10, &[0x10])]);
+
+        assert_eq!(b, [(0x10, vec![0x10]), (0x20, vec![0x20])]);
+    }
+
+    #[test]
+    fn write_top_of_address_space_does_not_overflow() {
+        let b = blocks(&[
+            (0xffff_fffe, &[0x00, 0x01, 0x02]),
+            (0xffff_fffc, &[0x00, 0x01]),
+        ]);
+
+        assert_eq!(b, [(0xffff_fffc, vec![0x00, 0x01, 0x00, 0x01])]);
+    }
+
+    #[test]
+    fn try_write_past_top_of_address_space_returns_err() {
+        let mut image = Image::new();
+
+        let r = image.try_write(0xffff_ffff, &[0x00, 0x01]);
+
+        assert_eq!(
+            r,
+            Err(AddressRangeOverflow {
+                address: 0xffff_ffff,
+                len: 2
+            })
+        );
+        assert!(image.is_empty());
+    }
+
+    #[test]
+    fn expand_to_alignment_merges_blocks_sharing_a_word() {
+        let mut image = Image::new()
+            .with_data(0x11, &[0x01])
+            .with_data(0x16, &[0x06])
+            .with_data(0xffff_fffd, &[0xfd]);
+
+        let padding = image.expand_to_alignment(8, 0x00);
+
+        assert_eq!(
+            image.blocks.into_iter().collect::<Vec<_>>(),
+            [
+                (0x10, vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x06, 0x00]),
+                (
+                    0xffff_fff8,
+                    vec![0x00, 0x00, 0x00, 0x00, 0x00, 0xfd, 0x00, 0x00]
+                ),
+            ]
+        );
+        assert_eq!(
+            padding,
+            [
+                Padding {
+                    address: 0x10,
+                    len: 1
+                },
+                Padding {
+                    address: 0x12,
+                    len: 4
+                },
+                Padding {
+                    address: 0x17,
+                    len: 1
+                },
+                Padding {
+                    address: 0xffff_fff8,
+                    len: 5
+                },
+                Padding {
+                    address: 0xffff_fffe,
+                    len: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn subtract_returns_changed_and_absent_bytes() {
+        let new = Image::new()
+            .with_data(0x10, &[0x00, 0x01, 0x02])
+            .with_data(0x20, &[0x20])
+            .with_data(0xffff_fffe, &[0xfe, 0xff]);
+        let old = Image::new()
+            .with_data(0x11, &[0x01])
+            .with_data(0x20, &[0x20, 0x21])
+            .with_data(0xffff_fffe, &[0xfe, 0x00]);
+
+        let delta = new.subtract(&old);
+
+        assert_eq!(
+            delta.blocks.into_iter().collect::<Vec<_>>(),
+            [
+                (0x10, vec![0x00]),
+                (0x12, vec![0x02]),
+                (0xffff_ffff, vec![0xff])
+            ]
+        );
+    }
+
+    #[test]
+    fn gaps_returns_unwritten_ranges() {
+        let image = Image::new()
+            .with_data(0x10, &[0x00; 4])
+            .with_data(0x18, &[0x00; 2]);
+
+        let gaps = image.gaps(0x0e, 0x10);
+
+        assert_eq!(gaps, [(0x0e, 2), (0x14, 4), (0x1a, 4)]);
+    }
+
+    #[test]
+    fn coalesce_top_of_address_space_does_not_merge_wrapped_record() {
+        let records = vec![
+            Record::S3(Data {
+                address: Address32(0xffff_fffe),
+                data: vec![0xfe, 0xff],
+            }),
+            Record::S5(Count16(1)),
+            Record::S3(Data {
+                address: Address32(0x0000_0000),
+                data: vec![0x00],
+            }),
+            Record::S3(Data {
+                address: Address32(0x0000_0001),
+                data: vec![],
+            }),
+            Record::S3(Data {
+                address: Address32(0x0000_0001),
+                data: vec![0x01],
+            }),
+        ];
+
+        let segments = coalesce(records).collect::<Vec<_>>();
+
+        assert_eq!(
+            segments,
+            [
+                Segment {
+                    address: 0xffff_fffe,
+                    data: vec![0xfe, 0xff],
+                },
+                Segment {
+                    address: 0x0000_0000,
+                    data: vec![0x00, 0x01],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Checking that a memory image fits the memory regions of a device
+use crate::image::Image;
+use std::fmt;
+
+/// A named region of a device's memory, e.g. a bank of flash
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct Region {
+    /// Name shown in reports
+    pub name: String,
+    /// Address of the region's first byte
+    pub start: u32,
+    /// Size of the region in bytes
+    pub size: u64,
+}
+
+impl Region {
+    /// Creates a region
+    pub fn new(name: impl Into<String>, start: u32, size: u64) -> Self {
+        Region {
+            name: name.into(),
+            start,
+            size,
+        }
+    }
+
+    /// Returns the address one past the region's last byte
+    pub fn end(&self) -> u64 {
+        self.start as u64 + self.size
+    }
+}
+
+/// How much of a [`Region`] an image uses
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct RegionUsage {
+    /// The region
+    pub region: Region,
+    /// Number of bytes of the image within the region
+    pub used: u64,
+}
+
+impl RegionUsage {
+    /// Returns the number of bytes of the region the image leaves unused
+    pub fn free(&self) -> u64 {
+        self.region.size.saturating_sub(self.used)
+    }
+
+    /// Returns the percentage of the region the image leaves unused, rounded
+    /// down
+    pub fn headroom_percent(&self) -> u64 {
+        match self.region.size {
+            0 => 0,
+            size => self.free() * 100 / size,
+        }
+    }
+}
+
+/// The result of [`budget_check`]: how much of each region an image uses, and
+/// how many of its bytes fall outside every region
+///
+/// Displays one line per region, suitable for a CI log.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct BudgetReport {
+    /// Usage of each region, in the order given
+    pub regions: Vec<RegionUsage>,
+    /// Number of bytes of the image not within any region
+    pub outside: u64,
+}
+
+impl BudgetReport {
+    /// Returns true if no bytes fall outside the regions and every region
+    /// leaves at least `headroom_percent` of its size unused
+    ///
+    /// A `headroom_percent` of 0 only requires the image to fit.
+    pub fn fits(&self, headroom_percent: u64) -> bool {
+        self.outside == 0
+            && self.regions.iter().all(|usage| {
+                usage.free() as u128 * 100 >= usage.region.size as u128 * headroom_percent as u128
+            })
+    }
+}
+
+impl fmt::Display for BudgetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for usage in &self.regions {
+            writeln!(
+                f,
+                "{}: {} of {} bytes used at {:#010X}, {}% free",
+                usage.region.name,
+                usage.used,
+                usage.region.size,
+                usage.region.start,
+                usage.headroom_percent()
+            )?;
+        }
+
+        if self.outside > 0 {
+            writeln!(f, "{} bytes outside every region", self.outside)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the number of bytes of `image` within `start..end`
+fn bytes_within(image: &Image, start: u64, end: u64) -> u64 {
+    image
+        .blocks()
+        .map(|(address, block)| {
+            let block_start = (address as u64).max(start);
+            let block_end = (address as u64 + block.len() as u64).min(end);
+            block_end.saturating_sub(block_start)
+        })
+        .sum()
+}
+
+/// Measures how much of each region `image` uses, so a build can check that
+/// firmware fits its device with room to spare
+///
+/// Regions are expected not to overlap; bytes within more than one region are
+/// counted in each.
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::layout::{budget_check, Region};
+///
+/// let image = srec::Image::new().with_data(0x0800_0000, &[0x00; 900]);
+/// let regions = [Region::new("flash", 0x0800_0000, 1024)];
+///
+/// let report = budget_check(&image, &regions);
+///
+/// assert_eq!(report.regions[0].used, 900);
+/// assert!(report.fits(10));
+/// assert!(!report.fits(20));
+/// assert_eq!(
+///     report.to_string(),
+///     "flash: 900 of 1024 bytes used at 0x08000000, 12% free\n"
+/// );
+/// ```
+pub fn budget_check(image: &Image, regions: &[Region]) -> BudgetReport {
+    let regions = regions
+        .iter()
+        .map(|region| RegionUsage {
+            region: region.clone(),
+            used: bytes_within(image, region.start as u64, region.end()),
+        })
+        .collect::<Vec<_>>();
+
+    let inside = regions.iter().map(|usage| usage.used).sum::<u64>();
+
+    BudgetReport {
+        outside: image.len().saturating_sub(inside),
+        regions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget_check_bytes_outside_regions_do_not_fit() {
+        let image = Image::new()
+            .with_data(0x0ffe, &[0x00; 4])
+            .with_data(0x2000, &[0x00; 2]);
+        let regions = [
+            Region::new("boot", 0x0000, 0x1000),
+            Region::new("app", 0x1000, 0x1000),
+        ];
+
+        let report = budget_check(&image, &regions);
+
+        assert_eq!(report.regions[0].used, 2);
+        assert_eq!(report.regions[1].used, 2);
+        assert_eq!(report.outside, 2);
+        assert!(!report.fits(0));
+        assert_eq!(
+            report.to_string(),
+            "boot: 2 of 4096 bytes used at 0x00000000, 99% free\n\
+             app: 2 of 4096 bytes used at 0x00001000, 99% free\n\
+             2 bytes outside every region\n"
+        );
+    }
+}
@@ -1,6 +1,26 @@
 //! Parsing (reading) and generation (writing) of [Motorola
 //! S-record](https://en.wikipedia.org/wiki/SREC_\(file_format\)) (also known
 //! as SRECORD or SREC) files
+//!
+//! No public parsing or encoding function panics, whatever its input - invalid
+//! input is reported through the returned error types instead. This is checked
+//! by denying `unwrap`/`expect` in the library and by the fuzz targets in
+//! `fuzz/`.
+//!
+//! # Features
+//!
+//! - `reader` (default): parsing of records and files, and the [`analyze`],
+//!   [`convert`], [`file`] and [`verify`] modules built on it
+//! - `writer` (default): generation of records and files, and with `reader`
+//!   the [`document`] module for editing files in place
+//! - `testing`: the `assert_srec_eq!` macro and other helpers for comparing
+//!   records in tests, which report only the records that differ
+//! - `tokio`: `reader::read_records_async`, which reads records from tokio's
+//!   asynchronous IO traits as a stream
+//!
+//! The record types and the [`digest`], [`image`] and [`layout`] modules are
+//! always available, so users needing only one direction
+//! can disable the other with `default-features = false`.
 #![deny(
     missing_docs,
     missing_debug_implementations,
@@ -13,12 +33,46 @@
     unused_qualifications
 )]
 #![warn(clippy::cargo)]
+#![cfg_attr(not(test), deny(clippy::unwrap_used, clippy::expect_used))]
 
+#[cfg(feature = "reader")]
+pub mod analyze;
+#[cfg(feature = "writer")]
 mod checksum;
+#[cfg(feature = "reader")]
+pub mod convert;
+pub mod digest;
+#[cfg(all(feature = "reader", feature = "writer"))]
+pub mod document;
+#[cfg(feature = "reader")]
+mod error;
+#[cfg(feature = "writer")]
+pub mod fieldmap;
+#[cfg(feature = "reader")]
+pub mod file;
+pub mod flavor;
+pub mod header;
+pub mod image;
+pub mod layout;
+#[cfg(feature = "reader")]
 pub mod reader;
 mod record;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "reader")]
+pub mod verify;
+#[cfg(feature = "writer")]
 pub mod writer;
 
+#[cfg(feature = "reader")]
+pub use error::Error;
+#[cfg(feature = "reader")]
+pub use file::SrecFile;
+pub use flavor::Flavor;
+pub use header::HeaderInfo;
+pub use image::Image;
+#[cfg(feature = "reader")]
 pub use reader::{read_records, Error as ReaderError};
 pub use record::*;
-pub use writer::generate_srec_file;
+#[cfg(feature = "writer")]
+pub use writer::{generate_srec_file, Error as WriterError};
@@ -1,147 +1,590 @@
 //! Parsing of SREC records and files
-use crate::checksum::checksum_of;
+use crate::image::Image;
 use crate::record::*;
+use std::cell::Cell;
 use std::error;
 use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::mem;
+use std::num::Wrapping;
+use std::ops::{Bound, Range, RangeBounds};
+use std::path::Path;
 use std::str::{self, FromStr};
-
-#[derive(Debug, PartialEq)]
-struct RawRecord {
-    t: u8,
-    bytes: Vec<u8>,
-}
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "tokio")]
+mod stream;
+/// Summary statistics of the records read from a file, as
+/// [`FileStats`](crate::analyze::FileStats)
+pub use crate::analyze::FileStats as Stats;
+#[cfg(feature = "tokio")]
+pub use stream::{read_records_async, read_records_async_with, AsyncRecords};
+mod validate;
+pub use validate::{
+    read_records_validated, validate, validate_with, ValidationError, ValidationOptions,
+};
 
 /// Errors which may occur during reading
-#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
 pub enum Error {
     /// String did not have enough characters
     NotEnoughData,
-    /// Next character was unexpected
+    /// Next character was unexpected: the record did not start with "S"
     UnexpectedCharacter,
+    /// Record type was not a digit, or was a digit for a type which is not
+    /// known (S4). Holds the type character.
+    InvalidRecordType(char),
+    /// Byte count field was not a pair of hex digits
+    InvalidByteCount,
+    /// Character in a hex field (the payload or checksum) was not a hex digit
+    InvalidHexDigit {
+        /// Field holding the character
+        field: HexField,
+        /// Position of the character in the record, starting from 1
+        column: usize,
+        /// The character found
+        found: char,
+    },
     /// Record byte count field was zero (must be >= 1)
     ByteCountZero,
     /// Record checksum did not match calculated checksum
-    ChecksumMismatch,
+    ChecksumMismatch {
+        /// Record type, the digit following the "S"
+        t: u8,
+        /// Address field of the record, if its type has one and the record
+        /// was long enough to hold it
+        address: Option<u32>,
+        /// Checksum given by the record
+        found: u8,
+        /// Checksum calculated from the record's contents
+        computed: u8,
+    },
+    /// Record byte count field was smaller than the minimum for the record
+    /// type (only reported in strict mode)
+    ByteCountTooSmall,
+    /// Next character was not ASCII
+    NonAsciiCharacter,
+    /// Line was longer than [`ReaderOptions::max_line_length`]
+    LineTooLong,
+    /// S0 header data was not valid UTF-8. Holds the header data bytes, which
+    /// can be shown with [`String::from_utf8_lossy`] or
+    /// [`RawRecord::header_lossy`].
+    InvalidHeaderEncoding(Vec<u8>),
+    /// Final line of the input ended part way through a record, as when a
+    /// transfer is interrupted (only reported with
+    /// [`ReaderOptions::report_truncation`]). Holds the partial line.
+    TruncatedFinalRecord(String),
+    /// S0 header record address field was not 0x0000 (only reported in strict
+    /// mode)
+    NonZeroHeaderAddress,
+    /// Input was text in an encoding other than ASCII, such as UTF-16 written
+    /// by some Windows editors
+    UnsupportedEncoding(Encoding),
 }
 
 impl error::Error for Error {}
 
-impl fmt::Display for Error {
+/// Text encodings recognised so they can be reported by
+/// [`Error::UnsupportedEncoding`]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+pub enum Encoding {
+    /// UTF-16, little endian
+    Utf16Le,
+    /// UTF-16, big endian
+    Utf16Be,
+}
+
+impl fmt::Display for Encoding {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "{}",
             match self {
-                Error::NotEnoughData => "not enough data",
-                Error::UnexpectedCharacter => "unexpected character",
-                Error::ByteCountZero => "byte count zero",
-                Error::ChecksumMismatch => "checksum mismatch",
+                Encoding::Utf16Le => "UTF-16LE",
+                Encoding::Utf16Be => "UTF-16BE",
             }
         )
     }
 }
 
-impl FromStr for RawRecord {
-    type Err = Error;
+/// Returns the encoding of text which is UTF-16 rather than ASCII, recognised
+/// by its byte order mark or by NULs in every other byte
+fn detect_wide_encoding(s: &[u8]) -> Option<Encoding> {
+    match s {
+        [0xff, 0xfe, ..] => return Some(Encoding::Utf16Le),
+        [0xfe, 0xff, ..] => return Some(Encoding::Utf16Be),
+        _ => {}
+    }
 
-    // Using is_empty would ruin the consistency of checking if there are enough
-    // characters between 1 and 2 required
-    #[allow(clippy::len_zero)]
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        // Read initial "S" character
-        if s.len() < 1 {
-            return Err(Error::NotEnoughData);
-        }
+    let pairs = s[..s.len().min(64)].chunks_exact(2);
+    let n = pairs.len();
+    let (le, be) = pairs.fold((0, 0), |(le, be), pair| match pair {
+        [c, 0] if *c != 0 => (le + 1, be),
+        [0, c] if *c != 0 => (le, be + 1),
+        _ => (le, be),
+    });
+
+    if n > 0 && le * 2 > n {
+        Some(Encoding::Utf16Le)
+    } else if n > 0 && be * 2 > n {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
 
-        let (first_char, s) = s.split_at(1);
+/// The hex fields of a record, for locating an [`Error::InvalidHexDigit`]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+pub enum HexField {
+    /// Payload: the address and data
+    Payload,
+    /// Checksum
+    Checksum,
+}
 
-        if first_char != "S" {
-            return Err(Error::UnexpectedCharacter);
-        }
+impl fmt::Display for HexField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                HexField::Payload => "payload",
+                HexField::Checksum => "checksum",
+            }
+        )
+    }
+}
 
-        // Read type field
-        if s.len() < 1 {
-            return Err(Error::NotEnoughData);
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotEnoughData => write!(f, "not enough data"),
+            Error::UnexpectedCharacter => write!(f, "unexpected character"),
+            Error::InvalidRecordType(t) => write!(f, "invalid record type {:?}", t),
+            Error::InvalidByteCount => write!(f, "invalid byte count"),
+            Error::InvalidHexDigit {
+                field,
+                column,
+                found,
+            } => write!(
+                f,
+                "invalid hex digit {:?} in {} at column {}",
+                found, field, column
+            ),
+            Error::ByteCountZero => write!(f, "byte count zero"),
+            Error::ChecksumMismatch {
+                t,
+                address,
+                found,
+                computed,
+            } => {
+                write!(f, "checksum mismatch in S{} record", t)?;
+                if let Some(address) = address {
+                    write!(f, " at {:#06X}", address)?;
+                }
+                write!(f, ": found {:#04X}, computed {:#04X}", found, computed)
+            }
+            Error::ByteCountTooSmall => write!(f, "byte count too small for record type"),
+            Error::NonAsciiCharacter => write!(f, "non-ASCII character"),
+            Error::LineTooLong => write!(f, "line too long"),
+            Error::InvalidHeaderEncoding(bytes) => write!(
+                f,
+                "header is not valid UTF-8: {:?}",
+                String::from_utf8_lossy(bytes)
+            ),
+            Error::TruncatedFinalRecord(partial) => {
+                write!(f, "truncated final record {:?}", partial)
+            }
+            Error::NonZeroHeaderAddress => write!(f, "header record address not zero"),
+            Error::UnsupportedEncoding(encoding) => {
+                write!(f, "input is {} encoded rather than ASCII", encoding)
+            }
         }
+    }
+}
 
-        let (type_str, s) = s.split_at(1);
+/// An [`Error`] with the location of the field in which it occurred
+///
+/// The span is a byte range within the line which was parsed, covering the
+/// whole of the failing field: the "S" character, type, byte count, a single
+/// payload byte, the checksum or an S0 record's header data. When the line
+/// ends part way through a field, the span covers what there is of the field
+/// (and so may be empty, at the end of the line).
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct SpannedError {
+    /// The error which occurred
+    pub error: Error,
+    /// Byte range of the failing field within the line
+    pub span: Range<usize>,
+}
 
-        let t = type_str
-            .parse::<u8>()
-            .map_err(|_| Error::UnexpectedCharacter)?;
+impl SpannedError {
+    /// Creates an error in the field at `span` of `s`, clamping the span to
+    /// the length of `s`
+    fn new(s: &[u8], error: Error, span: Range<usize>) -> Self {
+        let end = span.end.min(s.len());
+        let start = span.start.min(end);
 
-        // Read byte count field
-        if s.len() < 2 {
-            return Err(Error::NotEnoughData);
+        SpannedError {
+            error,
+            span: start..end,
         }
+    }
+}
+
+impl error::Error for SpannedError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl fmt::Display for SpannedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at bytes {}..{}",
+            self.error, self.span.start, self.span.end
+        )
+    }
+}
+
+/// Marks bytes in [`NIBBLES`] which are not hex digits
+const NOT_HEX: u8 = 0xff;
+
+/// Value of each byte as an ASCII hex digit, or [`NOT_HEX`], so digits are
+/// decoded with a single lookup rather than a chain of range checks
+const NIBBLES: [u8; 256] = {
+    let mut table = [NOT_HEX; 256];
+    let mut i = 0;
+    while i < 10 {
+        table[b'0' as usize + i] = i as u8;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 6 {
+        table[b'a' as usize + i] = 10 + i as u8;
+        table[b'A' as usize + i] = 10 + i as u8;
+        i += 1;
+    }
+    table
+};
+
+/// Returns the error for an unexpected byte `c`, distinguishing bytes which
+/// are not ASCII
+fn unexpected(c: u8) -> Error {
+    if c.is_ascii() {
+        Error::UnexpectedCharacter
+    } else {
+        Error::NonAsciiCharacter
+    }
+}
 
-        let (byte_count_str, s) = s.split_at(2);
+/// Returns the error for the byte `s[i]` of a hex field, which is not a hex
+/// digit (or not one accepted)
+fn invalid_hex_at(s: &[u8], i: usize, field: HexField) -> Error {
+    match s[i] {
+        c if c.is_ascii() => Error::InvalidHexDigit {
+            field,
+            column: i + 1,
+            found: c as char,
+        },
+        _ => Error::NonAsciiCharacter,
+    }
+}
 
-        let byte_count =
-            usize::from_str_radix(byte_count_str, 16).map_err(|_| Error::UnexpectedCharacter)?;
+/// Reads the byte encoded by the pair of hex digits starting at `s[i]`, which
+/// are part of `field`
+fn hex_byte_at(s: &[u8], i: usize, field: HexField) -> Result<u8, Error> {
+    let pair = s.get(i..i + 2).ok_or(Error::NotEnoughData)?;
+    let high = NIBBLES[pair[0] as usize];
+    let low = NIBBLES[pair[1] as usize];
+
+    // Both nibbles are valid exactly when neither has its high bits set
+    if (high | low) & 0xf0 == 0 {
+        Ok(high << 4 | low)
+    } else if high == NOT_HEX {
+        Err(invalid_hex_at(s, i, field))
+    } else {
+        Err(invalid_hex_at(s, i + 1, field))
+    }
+}
 
-        if byte_count == 0 {
-            return Err(Error::ByteCountZero);
-        }
+/// Reads the byte count field of a record
+fn byte_count_of(s: &[u8]) -> Result<u8, Error> {
+    let pair = s.get(2..4).ok_or(Error::NotEnoughData)?;
+    let high = NIBBLES[pair[0] as usize];
+    let low = NIBBLES[pair[1] as usize];
+
+    if (high | low) & 0xf0 == 0 {
+        Ok(high << 4 | low)
+    } else if pair.is_ascii() {
+        Err(Error::InvalidByteCount)
+    } else {
+        Err(Error::NonAsciiCharacter)
+    }
+}
+
+/// Returns the hex field holding the character `s[i]`, which follows the byte
+/// count
+fn hex_field_at(s: &[u8], i: usize) -> HexField {
+    match byte_count_of(s) {
+        Ok(byte_count) if i >= 2 + 2 * byte_count as usize => HexField::Checksum,
+        _ => HexField::Payload,
+    }
+}
+
+/// Returns the address field at the start of a record's payload, for record
+/// types which have one
+fn address_of(t: u8, bytes: &[u8]) -> Option<u32> {
+    let width = match t {
+        0 | 1 | 9 => 2,
+        2 | 8 => 3,
+        3 | 7 => 4,
+        _ => return None,
+    };
+
+    bytes
+        .get(..width)
+        .map(|b| b.iter().fold(0, |address, &b| address << 8 | u32::from(b)))
+}
+
+/// Returns the smallest byte count a record of type `t` can have: its address
+/// field plus the checksum
+fn min_byte_count(t: u8) -> Option<usize> {
+    match t {
+        0 | 1 | 5 | 9 => Some(3),
+        2 | 6 | 8 => Some(4),
+        3 | 7 => Some(5),
+        _ => None,
+    }
+}
+
+impl RawRecord {
+    fn parse(s: &[u8], options: &ReaderOptions) -> Result<Self, Error> {
+        RawRecord::parse_spanned(s, options).map_err(|e| e.error)
+    }
 
-        // Read payload bytes (including checksum)
-        let mut bytes: Vec<u8> = Vec::with_capacity(byte_count);
+    fn parse_spanned(s: &[u8], options: &ReaderOptions) -> Result<Self, SpannedError> {
+        // Index directly into the line's bytes rather than repeatedly slicing
+        // a string so long records are parsed in a single pass.
+        let err = |error, span| SpannedError::new(s, error, span);
+
+        // Checks of the whole record stop short of any trailing comment
+        let len = record_len(s);
+
+        if let Some(max) = options.max_line_length {
+            if len > max {
+                return Err(err(Error::LineTooLong, max..len));
+            }
+        }
 
-        let mut s = s;
-        for _ in 0..byte_count {
-            if s.len() < 2 {
-                return Err(Error::NotEnoughData);
+        if options.case_sensitive {
+            if let Some(i) = s[..len].iter().position(u8::is_ascii_lowercase) {
+                // Report the whole field containing the character
+                return Err(if i < 2 {
+                    err(Error::UnexpectedCharacter, i..i + 1)
+                } else if i < 4 {
+                    err(Error::InvalidByteCount, 2..4)
+                } else {
+                    err(
+                        invalid_hex_at(s, i, hex_field_at(s, i)),
+                        i - i % 2..i - i % 2 + 2,
+                    )
+                });
             }
+        }
+
+        // Read initial "S" character, accepting lowercase as emitted by some
+        // toolchains
+        match s.first() {
+            Some(b'S') | Some(b's') => {}
+            Some(&c) => return Err(err(unexpected(c), 0..1)),
+            None => return Err(err(Error::NotEnoughData, 0..1)),
+        }
+
+        // Read type field
+        let t = match s.get(1) {
+            Some(c @ b'0'..=b'9') => c - b'0',
+            Some(&c) if c.is_ascii() => return Err(err(Error::InvalidRecordType(c as char), 1..2)),
+            Some(_) => return Err(err(Error::NonAsciiCharacter, 1..2)),
+            None => return Err(err(Error::NotEnoughData, 1..2)),
+        };
+
+        // Read byte count field
+        let byte_count = byte_count_of(s).map_err(|e| err(e, 2..4))? as usize;
 
-            let (byte_str, s2) = s.split_at(2);
-            s = s2;
+        if byte_count == 0 {
+            return Err(err(Error::ByteCountZero, 2..4));
+        }
 
-            bytes.push(u8::from_str_radix(byte_str, 16).map_err(|_| Error::UnexpectedCharacter)?);
+        if options.strict && min_byte_count(t).is_some_and(|min| byte_count < min) {
+            return Err(err(Error::ByteCountTooSmall, 2..4));
         }
 
-        let checksum = bytes.pop().unwrap();
+        // Read payload bytes into a buffer of exactly their size, summing them
+        // as they are decoded so the checksum needs no second pass
+        let mut bytes: Vec<u8> = Vec::with_capacity(byte_count - 1);
+        let mut sum = Wrapping(byte_count as u8);
+
+        for i in 0..byte_count - 1 {
+            let at = 4 + 2 * i;
+            let b = hex_byte_at(s, at, HexField::Payload).map_err(|e| err(e, at..at + 2))?;
+            sum += Wrapping(b);
+            bytes.push(b);
+        }
 
-        // TODO: Calculate checksum without having to essentially clone the bytes, maybe make
-        // checksum_of take an iterator?
-        let mut checksum_bytes = vec![byte_count as u8];
-        checksum_bytes.extend(&bytes);
-        let checksum_valid = checksum == checksum_of(&checksum_bytes);
+        let checksum_at = 2 + 2 * byte_count;
+        let checksum = hex_byte_at(s, checksum_at, HexField::Checksum)
+            .map_err(|e| err(e, checksum_at..checksum_at + 2))?;
+        let computed = !sum.0;
 
-        if checksum_valid {
+        if checksum == computed || options.ignore_checksums {
             Ok(RawRecord { t, bytes })
         } else {
-            Err(Error::ChecksumMismatch)
+            let error = Error::ChecksumMismatch {
+                t,
+                address: address_of(t, &bytes),
+                found: checksum,
+                computed,
+            };
+            Err(err(error, checksum_at..checksum_at + 2))
         }
     }
 }
 
-impl FromStr for Record {
+impl FromStr for RawRecord {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let rr = RawRecord::from_str(s)?;
+        RawRecord::parse(s.as_bytes(), &ReaderOptions::default())
+    }
+}
 
+/// Reads the framing of a single record - the "S", type digit, byte count and
+/// checksum - without interpreting its payload
+///
+/// The checksum is verified, but the type is not checked against the known
+/// record types and the payload may be any length. Useful for building
+/// higher-level record types on top of the standard framing.
+///
+/// # Examples
+///
+/// ```rust
+/// let rr = srec::reader::raw_record_from_str("S4050000AABB95").unwrap();
+///
+/// assert_eq!(rr.t, 4);
+/// assert_eq!(rr.bytes, [0x00, 0x00, 0xaa, 0xbb]);
+/// ```
+pub fn raw_record_from_str(s: &str) -> Result<RawRecord, Error> {
+    s.parse()
+}
+
+impl Record {
+    /// Reads a single record from a line of ASCII text, without first
+    /// requiring it to be valid UTF-8
+    ///
+    /// Behaves exactly as [`str::parse`], except that bytes which are not
+    /// ASCII are reported as [`Error::NonAsciiCharacter`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let record = srec::Record::from_bytes(b"S9031234B6");
+    ///
+    /// assert_eq!(record, Ok(srec::Record::S9(srec::Address16(0x1234))));
+    /// assert_eq!(
+    ///     srec::Record::from_bytes(b"S9\xff31234B6"),
+    ///     Err(srec::reader::Error::NonAsciiCharacter)
+    /// );
+    /// ```
+    pub fn from_bytes(s: &[u8]) -> Result<Self, Error> {
+        Record::parse(s, &ReaderOptions::default())
+    }
+
+    /// Reads a single record from a line, reporting the location of the field
+    /// which could not be read on failure
+    ///
+    /// Otherwise behaves exactly as [`str::parse`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let err = srec::Record::parse_spanned("S107123400010203FF").unwrap_err();
+    ///
+    /// assert_eq!(err.error, srec::reader::Error::ChecksumMismatch {
+    ///         t: 1,
+    ///         address: Some(0x1234),
+    ///         found: 0xff,
+    ///         computed: 0xac,
+    ///     }
+    /// );
+    /// assert_eq!(err.span, 16..18);
+    /// ```
+    pub fn parse_spanned(s: &str) -> Result<Self, SpannedError> {
+        Record::parse_spanned_with(s.as_bytes(), &ReaderOptions::default())
+    }
+
+    pub(crate) fn parse(s: &[u8], options: &ReaderOptions) -> Result<Self, Error> {
+        Record::parse_spanned_with(s, options).map_err(|e| e.error)
+    }
+
+    fn parse_spanned_with(s: &[u8], options: &ReaderOptions) -> Result<Self, SpannedError> {
+        let rr = RawRecord::parse_spanned(s, options)?;
+
+        if options.strict && rr.t == 0 && rr.bytes.get(..2).is_some_and(|a| a != [0, 0]) {
+            return Err(SpannedError::new(s, Error::NonZeroHeaderAddress, 4..8));
+        }
+
+        // Record-level errors are caused by the type not being known, the
+        // header data not being UTF-8, or by the byte count not matching what
+        // the type requires
+        if options.unknown_records == UnknownRecords::Keep && min_byte_count(rr.t).is_none() {
+            return Ok(Record::Unknown {
+                t: rr.t,
+                bytes: rr.bytes,
+            });
+        }
+
+        // The checksum follows the tag, type, byte count and the bytes it
+        // counts, wherever the line itself ends
+        let checksum_offset = 4 + 2 * rr.bytes.len();
+
+        Record::from_raw(rr).map_err(|error| match error {
+            Error::InvalidRecordType(_) => SpannedError::new(s, error, 1..2),
+            Error::InvalidHeaderEncoding(_) => SpannedError::new(s, error, 8..checksum_offset),
+            _ => SpannedError::new(s, error, 2..4),
+        })
+    }
+
+    fn from_raw(rr: RawRecord) -> Result<Self, Error> {
         let r = match rr.t {
-            0 => Record::S0(
-                str::from_utf8(&rr.bytes[2..])
-                    .expect("Invalid UTF-8 bytes in S0 data")
-                    .trim_end_matches('\0')
-                    .into(),
-            ),
+            0 => {
+                if rr.bytes.len() < 2 {
+                    return Err(Error::NotEnoughData);
+                }
+
+                let s = str::from_utf8(&rr.bytes[2..])
+                    .map_err(|_| Error::InvalidHeaderEncoding(rr.bytes[2..].to_vec()))?;
+
+                Record::S0(s.trim_end_matches('\0').into())
+            }
             1 => {
                 if rr.bytes.len() < 2 {
                     return Err(Error::NotEnoughData);
                 }
 
-                let (address_bytes, data) = rr.bytes.split_at(2);
+                let mut data = rr.bytes;
 
                 let mut address = [0u8; 2];
-                address.copy_from_slice(address_bytes);
+                address.copy_from_slice(&data[..2]);
                 let address = u16::from_be_bytes(address);
+                data.drain(..2);
 
                 Record::S1(Data {
                     address: Address16(address),
-                    data: data.to_vec(),
+                    data,
                 })
             }
             2 => {
@@ -149,15 +592,16 @@ impl FromStr for Record {
                     return Err(Error::NotEnoughData);
                 }
 
-                let (address_bytes, data) = rr.bytes.split_at(3);
+                let mut data = rr.bytes;
 
                 let mut address = [0u8; 4];
-                address[1..].copy_from_slice(address_bytes);
+                address[1..].copy_from_slice(&data[..3]);
                 let address = u32::from_be_bytes(address);
+                data.drain(..3);
 
                 Record::S2(Data {
                     address: Address24(address),
-                    data: data.to_vec(),
+                    data,
                 })
             }
             3 => {
@@ -165,15 +609,16 @@ impl FromStr for Record {
                     return Err(Error::NotEnoughData);
                 }
 
-                let (address_bytes, data) = rr.bytes.split_at(4);
+                let mut data = rr.bytes;
 
                 let mut address = [0u8; 4];
-                address.copy_from_slice(address_bytes);
+                address.copy_from_slice(&data[..4]);
                 let address = u32::from_be_bytes(address);
+                data.drain(..4);
 
                 Record::S3(Data {
                     address: Address32(address),
-                    data: data.to_vec(),
+                    data,
                 })
             }
             5 => {
@@ -231,176 +676,2034 @@ impl FromStr for Record {
 
                 Record::S9(Address16(address))
             }
-            _ => return Err(Error::UnexpectedCharacter),
+            _ => return Err(Error::InvalidRecordType((b'0' + rr.t) as char)),
         };
 
         Ok(r)
     }
 }
 
-/// Reads records from a newline separated (either "\n" or "\r\n") string,
-/// returning an iterator over them
+impl FromStr for Record {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Record::from_bytes(s.as_bytes())
+    }
+}
+
+/// How [`read_records_with`] handles records of a type it does not know (S4)
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub enum UnknownRecords {
+    /// Report the record as [`Error::InvalidRecordType`]
+    #[default]
+    Error,
+    /// Skip the record without reading it
+    Skip,
+    /// Read the record as [`Record::Unknown`], still verifying its checksum
+    Keep,
+}
+
+/// How [`read_image`] handles a data record writing to addresses already
+/// written by an earlier record
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// Later records overwrite earlier ones
+    #[default]
+    Overwrite,
+    /// Earlier records are kept, and only the unwritten bytes of later
+    /// records are written
+    KeepFirst,
+    /// Report the overlap as [`crate::Error::Overlap`]
+    Error,
+}
+
+/// Options controlling how [`read_records_with`] reads records
 ///
-/// Does not validate file consistency as a whole - data records may overlap and
-/// start address records may be duplicated.
+/// The default options match the behaviour of [`read_records`].
 ///
 /// # Examples
 ///
 /// ```rust
-/// let mut records = srec::reader::read_records(
-///     "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n"
-/// );
+/// let options = srec::reader::ReaderOptions {
+///     strict: true,
+///     case_sensitive: true,
+///     max_line_length: Some(80),
+///     ..Default::default()
+/// };
 ///
-/// for record in records {
-///     println!("{:?}", record);
-/// }
+/// let mut records = srec::reader::read_records_with("s9031234b6\n", &options);
+///
+/// assert_eq!(
+///     records.next(),
+///     Some(Err(srec::reader::Error::UnexpectedCharacter))
+/// );
 /// ```
-pub fn read_records<'a>(s: &'a str) -> impl Iterator<Item = Result<Record, Error>> + 'a {
-    s.lines()
-        .map(|line| line.trim())
-        .filter(|line| !line.is_empty())
-        .map(|line| line.parse::<Record>())
+// Not comparable, as functions given for `skip_line_if` cannot be compared
+// reliably
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ReaderOptions {
+    /// Discard everything before the first 'S' or 's' on each line, allowing
+    /// records to be read from logs which prefix lines with e.g. timestamps.
+    /// Lines without an 'S' or 's' are left untouched.
+    pub skip_line_prefix: bool,
+    /// Report structural problems which are otherwise tolerated or reported
+    /// as more general errors:
+    ///
+    /// - byte counts smaller than the record type's address field plus
+    ///   checksum are reported as [`Error::ByteCountTooSmall`] before the
+    ///   payload is read
+    /// - S0 header records with an address other than 0x0000, which the
+    ///   standard requires, are reported as [`Error::NonZeroHeaderAddress`]
+    ///   rather than the address being ignored
+    pub strict: bool,
+    /// Skip keepalive lines - records with a valid checksum but no other
+    /// bytes (a byte count of 1, e.g. "S501FE") - which some serial bootloaders
+    /// interleave with the records they echo
+    pub skip_keepalives: bool,
+    /// Accept records whose checksum does not match, e.g. from tools which
+    /// write a placeholder checksum
+    pub ignore_checksums: bool,
+    /// Reject a lowercase "s" record tag and lowercase hex digits, which are
+    /// otherwise accepted. Any trailing comment may still be lowercase.
+    pub case_sensitive: bool,
+    /// Report records whose text (excluding surrounding whitespace and any
+    /// trailing comment, see [`split_comment`]) is longer than this many bytes
+    /// as [`Error::LineTooLong`], without reading them. Readers of streamed
+    /// input also stop buffering a line once it (excluding its line ending) is
+    /// longer than this, reporting it the same way, so untrusted input cannot
+    /// force large allocations. There is no limit by default;
+    /// [`ReaderOptions::bounded`] sets it to [`DEFAULT_MAX_LINE_LENGTH`].
+    pub max_line_length: Option<usize>,
+    /// How records of an unknown type are handled
+    pub unknown_records: UnknownRecords,
+    /// Report a final line which is not followed by a line ending and runs
+    /// out of characters part way through the record as
+    /// [`Error::TruncatedFinalRecord`] rather than [`Error::NotEnoughData`],
+    /// so an interrupted transfer can be told apart from corruption
+    pub report_truncation: bool,
+    /// Skip lines which do not start with an "S" record tag, such as banner
+    /// comments or log text interleaved by a build system, rather than
+    /// reporting them as errors
+    pub skip_non_records: bool,
+    /// Skip lines for which this returns true. It is given each line without
+    /// surrounding whitespace, after [`skip_line_prefix`](Self::skip_line_prefix)
+    /// is applied.
+    pub skip_line_if: Option<fn(&[u8]) -> bool>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn raw_record_from_str_empty_str_returns_err_not_enough_data() {
-        let s = "";
-
-        let rr = RawRecord::from_str(s);
-
-        assert_eq!(rr, Err(Error::NotEnoughData));
-    }
-
-    #[test]
-    fn raw_record_from_str_first_character_invalid_returns_err_unexpected_character() {
-        let s = "D";
-
-        let rr = RawRecord::from_str(s);
-
-        assert_eq!(rr, Err(Error::UnexpectedCharacter));
+impl ReaderOptions {
+    /// Returns the default options with lines limited to
+    /// [`DEFAULT_MAX_LINE_LENGTH`], for reading untrusted input
+    ///
+    /// These are the options used by [`read_records_from_reader`] and the
+    /// other readers of streamed input.
+    pub fn bounded() -> Self {
+        ReaderOptions {
+            max_line_length: Some(DEFAULT_MAX_LINE_LENGTH),
+            ..Default::default()
+        }
     }
 
-    #[test]
-    fn raw_record_from_str_no_type_value_returns_err_not_enough_data() {
-        let s = "S";
-
-        let rr = RawRecord::from_str(s);
-
-        assert_eq!(rr, Err(Error::NotEnoughData));
+    /// Returns options which tolerate the noise found in captured or logged
+    /// files: lines which are not records and keepalive records are skipped,
+    /// and records of unknown type are kept as [`Record::Unknown`]
+    ///
+    /// Checksums are still verified.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let options = srec::reader::ReaderOptions::lenient();
+    ///
+    /// let records = srec::reader::read_records_with(
+    ///     "Flashing...\nS501FE\nS9031234B6\n",
+    ///     &options,
+    /// )
+    /// .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(records, [Ok(srec::Record::S9(srec::Address16(0x1234)))]);
+    /// ```
+    pub fn lenient() -> Self {
+        ReaderOptions {
+            skip_keepalives: true,
+            skip_non_records: true,
+            unknown_records: UnknownRecords::Keep,
+            ..Default::default()
+        }
     }
 
-    #[test]
-    fn raw_record_from_str_invalid_type_value_returns_err_unexpected_character() {
-        let s = "Sx";
+    /// Applies the line-level options to a line, returning the text which
+    /// should be parsed as a record
+    pub(crate) fn prepare_line<'a>(&self, line: &'a [u8]) -> &'a [u8] {
+        let line = line.trim_ascii();
 
-        let rr = RawRecord::from_str(s);
+        if self.skip_line_prefix {
+            if let Some(i) = line.iter().position(|&c| matches!(c, b'S' | b's')) {
+                return &line[i..];
+            }
+        }
 
-        assert_eq!(rr, Err(Error::UnexpectedCharacter));
+        line
     }
 
-    #[test]
-    fn raw_record_from_str_byte_count_zero_returns_err_byte_count_zero() {
-        let s = "S100";
-
-        let rr = RawRecord::from_str(s);
-
-        assert_eq!(rr, Err(Error::ByteCountZero));
+    /// Returns true if a prepared line should not be parsed as a record
+    pub(crate) fn skips(&self, line: &[u8]) -> bool {
+        line.is_empty()
+            || (self.skip_non_records && !matches!(line, [b'S' | b's', ..]))
+            || self.skip_line_if.is_some_and(|skip| skip(line))
+            || (self.unknown_records == UnknownRecords::Skip
+                && matches!(line, [b'S' | b's', b'4', ..]))
+            || (self.skip_keepalives
+                && RawRecord::parse(line, &ReaderOptions::default())
+                    .is_ok_and(|rr| rr.bytes.is_empty()))
     }
+}
 
-    #[test]
-    fn raw_record_from_str_invalid_hex_character_returns_err_unexpected_character() {
-        let s = "S104123400xx";
-
-        let rr = RawRecord::from_str(s);
+/// Iterator over the lines of a string which can give exact size hints
+///
+/// The lines are only counted the first time a size hint is asked for, so
+/// reading without one scans the string once.
+#[derive(Debug, Clone)]
+struct CountedLines<'a> {
+    lines: str::Lines<'a>,
+    remaining: Cell<Option<usize>>,
+}
 
-        assert_eq!(rr, Err(Error::UnexpectedCharacter));
+impl<'a> CountedLines<'a> {
+    fn new(s: &'a str) -> Self {
+        CountedLines {
+            lines: s.lines(),
+            remaining: Cell::new(None),
+        }
     }
 
-    #[test]
-    fn raw_record_from_str_byte_count_too_large_returns_err_not_enough_data() {
-        let s = "S1100000FFEF";
-
-        let rr = RawRecord::from_str(s);
-
-        assert_eq!(rr, Err(Error::NotEnoughData));
+    fn counted(&self, line: Option<&'a str>) -> Option<&'a str> {
+        if line.is_some() {
+            self.remaining.set(self.remaining.get().map(|n| n - 1));
+        }
+        line
     }
+}
 
-    #[test]
-    fn raw_record_from_str_valid_record_empty_returns_ok_correct_raw_record() {
-        let s = "S101FE";
-
-        let rr = RawRecord::from_str(s);
+impl<'a> Iterator for CountedLines<'a> {
+    type Item = &'a str;
 
-        assert_eq!(
-            rr,
-            Ok(RawRecord {
-                t: 1,
-                bytes: vec![]
-            })
-        );
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.lines.next();
+        self.counted(line)
     }
 
-    #[test]
-    fn raw_record_from_str_valid_record_valid_checksum_returns_ok_correct_raw_record() {
-        let s = "S1101234000102030405060708090A0B0C5B";
-
-        let rr = RawRecord::from_str(s);
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = match self.remaining.get() {
+            Some(n) => n,
+            None => {
+                let n = self.lines.clone().count();
+                self.remaining.set(Some(n));
+                n
+            }
+        };
 
-        assert_eq!(
-            rr,
-            Ok(RawRecord {
-                t: 1,
-                bytes: vec![
-                    0x12, 0x34, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a,
-                    0x0b, 0x0c
-                ],
-            })
-        );
+        (remaining, Some(remaining))
     }
+}
 
-    #[test]
-    fn raw_record_from_str_valid_record_invalid_checksum_returns_ok_correct_raw_record() {
-        let s = "S1101234000102030405060708090A0B0CFF";
-
-        let rr = RawRecord::from_str(s);
-
-        assert_eq!(rr, Err(Error::ChecksumMismatch));
+impl DoubleEndedIterator for CountedLines<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let line = self.lines.next_back();
+        self.counted(line)
     }
+}
 
-    #[test]
-    fn s0_empty_string_from_str_returns_correct_record() {
-        let s = "S0030000FC";
-
-        let r = s.parse::<Record>();
-
-        assert_eq!(r, Ok(Record::S0("".into())));
-    }
+impl ExactSizeIterator for CountedLines<'_> {}
 
-    #[test]
-    fn s0_simple_string_from_str_returns_correct_record() {
+/// Reads records from a newline separated (either "\n" or "\r\n") string,
+/// returning an iterator over them
+///
+/// Does not validate file consistency as a whole - data records may overlap and
+/// start address records may be duplicated. See [`validate`] and
+/// [`read_records_validated`] for that.
+///
+/// The "S" record tag and hex digits are read case-insensitively, so records
+/// such as "s107123400010203ac" are accepted.
+///
+/// The iterator can be consumed from either end, and its size hint is bounded
+/// above by the number of lines in the string. Blank lines are skipped, so the
+/// iterator cannot know its exact length without parsing.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut records = srec::reader::read_records(
+///     "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n"
+/// );
+///
+/// for record in records {
+///     println!("{:?}", record);
+/// }
+/// ```
+///
+/// Finding the start address record without reading the whole file:
+///
+/// ```rust
+/// let start = srec::reader::read_records(
+///     "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n"
+/// )
+/// .rev()
+/// .next();
+///
+/// assert_eq!(start, Some(Ok(srec::Record::S9(srec::Address16(0x1234)))));
+/// ```
+pub fn read_records<'a>(s: &'a str) -> impl DoubleEndedIterator<Item = Result<Record, Error>> + 'a {
+    read_records_with(s, &ReaderOptions::default())
+}
+
+/// Reads records from a newline separated (either "\n" or "\r\n") string
+/// using the provided options, returning an iterator over them
+///
+/// Otherwise behaves exactly as [`read_records`].
+///
+/// # Examples
+///
+/// ```rust
+/// let options = srec::reader::ReaderOptions {
+///     skip_line_prefix: true,
+///     ..Default::default()
+/// };
+///
+/// let mut records = srec::reader::read_records_with(
+///     "[00:01.250] S00600004844521B\n[00:01.300] S9031234B6\n",
+///     &options,
+/// );
+///
+/// assert_eq!(records.next(), Some(Ok(srec::Record::S0("HDR".into()))));
+/// assert_eq!(
+///     records.next(),
+///     Some(Ok(srec::Record::S9(srec::Address16(0x1234))))
+/// );
+/// assert_eq!(records.next(), None);
+/// ```
+pub fn read_records_with<'a>(
+    s: &'a str,
+    options: &ReaderOptions,
+) -> impl DoubleEndedIterator<Item = Result<Record, Error>> + 'a {
+    let options = *options;
+
+    // Offset of the start of an unterminated final line
+    let tail = if options.report_truncation && !s.ends_with('\n') {
+        Some(s.rfind('\n').map_or(0, |i| i + 1))
+    } else {
+        None
+    };
+    let base = s.as_ptr() as usize;
+
+    CountedLines::new(s)
+        .map(move |line| options.prepare_line(line.as_bytes()))
+        .filter(move |line| !options.skips(line))
+        .map(move |line| match Record::parse(line, &options) {
+            Err(Error::NotEnoughData)
+                if tail.is_some_and(|tail| line.as_ptr() as usize - base >= tail) =>
+            {
+                Err(Error::TruncatedFinalRecord(
+                    String::from_utf8_lossy(line).into(),
+                ))
+            }
+            r => r,
+        })
+}
+
+/// Reads records from newline separated (either "\n" or "\r\n") ASCII text,
+/// returning an iterator over them
+///
+/// Unlike [`read_records`], the text does not need to be valid UTF-8 - bytes
+/// which are not ASCII are reported as [`Error::NonAsciiCharacter`] in the
+/// record they appear in. Text which is UTF-16 encoded, recognised by its byte
+/// order mark or by NULs in every other byte, is reported as a single
+/// [`Error::UnsupportedEncoding`] rather than an error for every line.
+/// Otherwise behaves exactly as [`read_records`].
+///
+/// # Examples
+///
+/// ```rust
+/// let mut records = srec::reader::read_records_from_bytes(
+///     b"S00600004844521B\nS1\xff7123400010203AC\nS9031234B6\n",
+/// );
+///
+/// assert_eq!(records.next(), Some(Ok(srec::Record::S0("HDR".into()))));
+/// assert_eq!(
+///     records.next(),
+///     Some(Err(srec::reader::Error::NonAsciiCharacter))
+/// );
+/// assert_eq!(
+///     records.next(),
+///     Some(Ok(srec::Record::S9(srec::Address16(0x1234))))
+/// );
+/// assert_eq!(records.next(), None);
+/// ```
+pub fn read_records_from_bytes<'a>(
+    s: &'a [u8],
+) -> impl DoubleEndedIterator<Item = Result<Record, Error>> + 'a {
+    let encoding = detect_wide_encoding(s);
+    let s = if encoding.is_some() { &s[..0] } else { s };
+
+    encoding
+        .map(|encoding| Err(Error::UnsupportedEncoding(encoding)))
+        .into_iter()
+        .chain(read_lines_with(
+            s.split(|&c| c == b'\n'),
+            &ReaderOptions::default(),
+        ))
+}
+
+fn read_lines_with<'a>(
+    lines: impl DoubleEndedIterator<Item = &'a [u8]> + 'a,
+    options: &ReaderOptions,
+) -> impl DoubleEndedIterator<Item = Result<Record, Error>> + 'a {
+    let options = *options;
+
+    lines
+        .map(move |line| options.prepare_line(line))
+        .filter(move |line| !options.skips(line))
+        .map(move |line| Record::parse(line, &options))
+}
+
+/// Reads records from lines which have already been split, e.g. by a log
+/// parser or protocol decoder, returning an iterator over them
+///
+/// Each line may still carry its line ending. Lines are otherwise read exactly
+/// as by [`read_records`], without needing to be joined into one string first.
+///
+/// # Examples
+///
+/// ```rust
+/// let lines = vec!["S00600004844521B", "S9031234B6\r\n"];
+///
+/// let mut records = srec::reader::read_records_from_lines(lines);
+///
+/// assert_eq!(records.next(), Some(Ok(srec::Record::S0("HDR".into()))));
+/// assert_eq!(
+///     records.next(),
+///     Some(Ok(srec::Record::S9(srec::Address16(0x1234))))
+/// );
+/// assert_eq!(records.next(), None);
+/// ```
+pub fn read_records_from_lines<'a, I>(lines: I) -> impl Iterator<Item = Result<Record, Error>> + 'a
+where
+    I: IntoIterator<Item = &'a str>,
+    I::IntoIter: 'a,
+{
+    let options = ReaderOptions::default();
+
+    lines
+        .into_iter()
+        .map(move |line| options.prepare_line(line.as_bytes()))
+        .filter(move |line| !options.skips(line))
+        .map(move |line| Record::parse(line, &options))
+}
+
+/// Returns a line without its "\n" or "\r\n" line ending
+pub(crate) fn strip_line_ending(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Line length limit set by [`ReaderOptions::bounded`], and so used by the
+/// readers of streamed input by default, comfortably above the longest
+/// possible record (514 characters)
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 1024;
+
+/// Iterator over the records read from a [`BufRead`], returned by
+/// [`read_records_from_reader`] and [`read_records_from_path`]
+#[derive(Debug)]
+pub struct ReadRecords<R> {
+    reader: R,
+    options: ReaderOptions,
+    cancel: Option<Arc<AtomicBool>>,
+    line: Vec<u8>,
+    line_number: usize,
+    done: bool,
+}
+
+impl<R> ReadRecords<R> {
+    /// Sets a flag which cancels reading once set, returning the iterator so
+    /// calls can be chained
+    ///
+    /// The flag is checked before each line is read, so another thread (e.g.
+    /// a GUI's cancel button) can stop reading a huge file promptly. Once it
+    /// is set the iterator yields [`crate::Error::Cancelled`], then ends.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let cancel = Arc::new(AtomicBool::new(false));
+    /// let mut records = srec::reader::read_records_from_reader(
+    ///     "S00600004844521B\nS9031234B6\n".as_bytes(),
+    /// )
+    /// .with_cancel_flag(cancel.clone());
+    ///
+    /// assert!(records.next().unwrap().is_ok());
+    /// cancel.store(true, Ordering::Relaxed);
+    /// assert!(matches!(records.next(), Some(Err(srec::Error::Cancelled))));
+    /// assert!(records.next().is_none());
+    /// ```
+    pub fn with_cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+}
+
+impl<R: BufRead> ReadRecords<R> {
+    /// Returns the number of lines read so far, which is the line number of
+    /// the record last returned
+    pub(crate) fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// Reads the next line into the buffer, reading no more than the length
+    /// limit and discarding the rest of a longer line
+    ///
+    /// Returns the number of bytes read, and whether the line was too long.
+    fn read_line(&mut self) -> io::Result<(usize, bool)> {
+        let max = match self.options.max_line_length {
+            Some(max) => max,
+            None => return Ok((self.reader.read_until(b'\n', &mut self.line)?, false)),
+        };
+
+        // Allow for a "\r\n" line ending beyond the limit
+        let limit = max as u64 + 2;
+        let n = (&mut self.reader)
+            .take(limit)
+            .read_until(b'\n', &mut self.line)?;
+
+        let too_long = strip_line_ending(&self.line).len() > max;
+        if too_long && !self.line.ends_with(b"\n") {
+            self.reader.skip_until(b'\n')?;
+        }
+
+        Ok((n, too_long))
+    }
+}
+
+impl<R: BufRead> Iterator for ReadRecords<R> {
+    type Item = Result<Record, crate::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            if self
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+            {
+                self.done = true;
+                return Some(Err(crate::Error::Cancelled));
+            }
+
+            self.line.clear();
+            match self.read_line() {
+                Ok((0, _)) => self.done = true,
+                Ok((_, too_long)) => {
+                    self.line_number += 1;
+                    let line_number = self.line_number;
+
+                    if too_long {
+                        return Some(Err(crate::Error::Syntax {
+                            line: line_number,
+                            error: Error::LineTooLong,
+                        }));
+                    }
+
+                    let line = self.options.prepare_line(&self.line);
+                    if !self.options.skips(line) {
+                        return Some(Record::parse(line, &self.options).map_err(|error| {
+                            crate::Error::Syntax {
+                                line: line_number,
+                                error,
+                            }
+                        }));
+                    }
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(crate::Error::Io(err)));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Reads records from a source of newline separated (either "\n" or "\r\n")
+/// text, returning an iterator over them
+///
+/// Lines are read one at a time as the iterator is consumed, so memory use is
+/// bounded by the longest line rather than the size of the source, and lines
+/// longer than [`DEFAULT_MAX_LINE_LENGTH`] are reported as
+/// [`Error::LineTooLong`] without being buffered whole. Errors carry the line
+/// number they occurred on, and a failure of `r` itself ends the iteration
+/// after being reported. Lines are otherwise read exactly as by
+/// [`read_records`].
+///
+/// # Examples
+///
+/// ```rust
+/// let s = "S00600004844521B\nS107123400010203FF\nS9031234B6\n";
+///
+/// let mut records = srec::reader::read_records_from_reader(s.as_bytes());
+///
+/// assert_eq!(records.next().unwrap().unwrap(), srec::Record::S0("HDR".into()));
+/// assert!(matches!(
+///     records.next(),
+///     Some(Err(srec::Error::Syntax {
+///         line: 2,
+///         error: srec::reader::Error::ChecksumMismatch { .. }
+///     }))
+/// ));
+/// ```
+pub fn read_records_from_reader<R: BufRead>(r: R) -> ReadRecords<R> {
+    read_records_from_reader_with(r, &ReaderOptions::bounded())
+}
+
+/// Reads records from a source of newline separated (either "\n" or "\r\n")
+/// text using the provided options, returning an iterator over them
+///
+/// [`ReaderOptions::max_line_length`] also limits how much of each line is
+/// buffered. Otherwise behaves exactly as [`read_records_from_reader`].
+///
+/// # Examples
+///
+/// ```rust
+/// let options = srec::reader::ReaderOptions {
+///     max_line_length: Some(16),
+///     ..Default::default()
+/// };
+/// let s = "S9031234B6\nS107123400010203AC\n";
+///
+/// let mut records = srec::reader::read_records_from_reader_with(s.as_bytes(), &options);
+///
+/// assert!(records.next().unwrap().is_ok());
+/// assert!(matches!(
+///     records.next(),
+///     Some(Err(srec::Error::Syntax {
+///         line: 2,
+///         error: srec::reader::Error::LineTooLong
+///     }))
+/// ));
+/// ```
+pub fn read_records_from_reader_with<R: BufRead>(r: R, options: &ReaderOptions) -> ReadRecords<R> {
+    ReadRecords {
+        reader: r,
+        options: *options,
+        cancel: None,
+        line: Vec::new(),
+        line_number: 0,
+        done: false,
+    }
+}
+
+/// Opens the file at `path` and reads records from it, returning an iterator
+/// over them
+///
+/// The file is streamed rather than read into memory up front, which matters
+/// for archives of many concatenated files. Otherwise behaves exactly as
+/// [`read_records_from_reader`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// for record in srec::reader::read_records_from_path("firmware.mot")? {
+///     println!("{:?}", record?);
+/// }
+/// # Ok::<(), srec::Error>(())
+/// ```
+pub fn read_records_from_path(path: impl AsRef<Path>) -> io::Result<ReadRecords<BufReader<File>>> {
+    Ok(read_records_from_reader(BufReader::new(File::open(path)?)))
+}
+
+/// Reads records from a newline separated (either "\n" or "\r\n") string,
+/// collecting the records which could be read and the errors for those which
+/// could not
+///
+/// Useful for recovering the rest of a file with a few corrupted lines. Each
+/// error is paired with its line number, starting from 1. Lines are otherwise
+/// read exactly as by [`read_records`].
+///
+/// # Examples
+///
+/// ```rust
+/// let (records, errors) = srec::reader::read_records_lossy(
+///     "S00600004844521B\nS107123400010203FF\nS9031234B6\n"
+/// );
+///
+/// assert_eq!(
+///     records,
+///     [
+///         srec::Record::S0("HDR".into()),
+///         srec::Record::S9(srec::Address16(0x1234)),
+///     ]
+/// );
+/// assert_eq!(errors, [(2, srec::reader::Error::ChecksumMismatch {
+///             t: 1,
+///             address: Some(0x1234),
+///             found: 0xff,
+///             computed: 0xac,
+///         }
+///     )]);
+/// ```
+pub fn read_records_lossy(s: &str) -> (Vec<Record>, Vec<(usize, Error)>) {
+    let options = ReaderOptions::default();
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+
+    for (i, line) in s.lines().enumerate() {
+        let line = options.prepare_line(line.as_bytes());
+        if options.skips(line) {
+            continue;
+        }
+
+        match Record::parse(line, &options) {
+            Ok(record) => records.push(record),
+            Err(error) => errors.push((i + 1, error)),
+        }
+    }
+
+    (records, errors)
+}
+
+/// A record read by [`read_records_located`], with its location in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocatedRecord {
+    /// Line number, starting from 1
+    pub line: usize,
+    /// Byte range of the record's text within the input, excluding any
+    /// surrounding whitespace, trailing comment and the line ending
+    pub offset: Range<usize>,
+    /// The record, or the reason it could not be read
+    pub record: Result<Record, Error>,
+    /// Text following the record's checksum on the same line, as split by
+    /// [`split_comment`]
+    pub comment: Option<String>,
+}
+
+/// Returns the length of the record at the start of a line, as given by its
+/// byte count, or the length of the line if it does not have one
+fn record_len(s: &[u8]) -> usize {
+    match (s.first(), byte_count_of(s)) {
+        (Some(b'S') | Some(b's'), Ok(byte_count)) => s.len().min(4 + 2 * byte_count as usize),
+        _ => s.len(),
+    }
+}
+
+/// Splits a line into the record it starts with and the text following the
+/// record's checksum, if there is any
+///
+/// Some tools annotate records by appending text after the checksum, such as
+/// "; reset vector". The end of the record is found from its byte count, and
+/// the text after it is returned without surrounding whitespace. Reading a
+/// record ignores such text, so this only needs to be used to keep it.
+///
+/// # Examples
+///
+/// ```rust
+/// let (record, comment) = srec::reader::split_comment("S9031234B6 ; reset vector");
+///
+/// assert_eq!(record, "S9031234B6");
+/// assert_eq!(comment, Some("; reset vector"));
+/// assert_eq!(srec::reader::split_comment("S9031234B6"), ("S9031234B6", None));
+/// ```
+pub fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let line = line.trim();
+    let len = record_len(line.as_bytes());
+
+    match line.get(..len) {
+        Some(record) => {
+            let comment = line[len..].trim();
+            (record, Some(comment).filter(|comment| !comment.is_empty()))
+        }
+        None => (line, None),
+    }
+}
+
+/// Reads records from a newline separated (either "\n" or "\r\n") string,
+/// returning an iterator over them along with their location in the string
+///
+/// The byte offsets index directly into `s`, so records can be mapped back to
+/// their exact position for in-place edits, and any text following a record
+/// is returned as its comment. Lines are otherwise read exactly as by
+/// [`read_records`].
+///
+/// # Examples
+///
+/// ```rust
+/// let s = "S00600004844521B\r\n  S9031234B6\r\n";
+///
+/// let located = srec::reader::read_records_located(s).collect::<Vec<_>>();
+///
+/// assert_eq!(located[1].line, 2);
+/// assert_eq!(located[1].offset, 20..30);
+/// assert_eq!(&s[located[1].offset.clone()], "S9031234B6");
+/// ```
+pub fn read_records_located(s: &str) -> impl Iterator<Item = LocatedRecord> + '_ {
+    let options = ReaderOptions::default();
+    let mut line_start = 0;
+
+    s.split_inclusive('\n')
+        .enumerate()
+        .filter_map(move |(i, line)| {
+            let offset = line_start;
+            line_start += line.len();
+
+            let text = options.prepare_line(line.as_bytes());
+            if options.skips(text) {
+                return None;
+            }
+
+            // The prepared text is a subslice of the line
+            let start = offset + (text.as_ptr() as usize - line.as_ptr() as usize);
+            let len = record_len(text);
+            let comment = str::from_utf8(&text[len..])
+                .map(str::trim)
+                .ok()
+                .filter(|comment| !comment.is_empty());
+
+            Some(LocatedRecord {
+                line: i + 1,
+                offset: start..start + len,
+                record: Record::parse(text, &options),
+                comment: comment.map(String::from),
+            })
+        })
+}
+
+/// Reads records from a newline separated (either "\n" or "\r\n") string
+/// holding several modules, grouping them into one [`Module`] per module
+///
+/// A module ends after its start address record (S7, S8 or S9), or where a
+/// header record (S0) begins the next. Records are otherwise read exactly as
+/// by [`read_records`], and the first line which cannot be read is returned as
+/// an error with its line number.
+///
+/// # Examples
+///
+/// ```rust
+/// let modules = srec::reader::read_modules(
+///     "S00600004844521B\nS107123400010203AC\nS9031234B6\n\
+///      S00600004844521B\nS10712380405060798\nS5030001FB\n",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(modules.len(), 2);
+/// assert_eq!(modules[0].start, Some(0x1234));
+/// assert_eq!(modules[1].count, Some(1));
+/// ```
+pub fn read_modules(s: &str) -> Result<Vec<Module>, crate::Error> {
+    let mut modules = Vec::new();
+    let mut module = Module::default();
+
+    for LocatedRecord { line, record, .. } in read_records_located(s) {
+        let record = record.map_err(|error| crate::Error::Syntax { line, error })?;
+
+        match record.memory_effect() {
+            MemoryEffect::Header(header) => {
+                if !module.is_empty() {
+                    modules.push(mem::take(&mut module));
+                }
+                module.header = Some(header.into());
+            }
+            MemoryEffect::Count(count) => module.count = Some(count),
+            MemoryEffect::StartAddress(start) => {
+                module.start = Some(start);
+                modules.push(mem::take(&mut module));
+            }
+            MemoryEffect::Write { .. } | MemoryEffect::Unknown { .. } => module.data.push(record),
+        }
+    }
+
+    if !module.is_empty() {
+        modules.push(module);
+    }
+
+    Ok(modules)
+}
+
+/// Reads records from a newline separated (either "\n" or "\r\n") string,
+/// merging the data they write into an [`Image`]
+///
+/// Records are read exactly as by [`read_records`], and the first line which
+/// cannot be read is returned as an error with its line number. Data records
+/// may appear in any order; where they overlap, `policy` decides which data is
+/// kept. Data running past the end of the 32-bit address space is reported as
+/// [`crate::Error::AddressRangeOverflow`]. Records other than data records are
+/// ignored.
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::reader::OverlapPolicy;
+///
+/// let s = "S107123400010203AC\nS10412360AA9\nS9031234B6\n";
+///
+/// let image = srec::reader::read_image(s, OverlapPolicy::KeepFirst).unwrap();
+///
+/// assert_eq!(
+///     image.blocks().collect::<Vec<_>>(),
+///     [(0x1234, &[0x00, 0x01, 0x02, 0x03][..])]
+/// );
+/// assert!(srec::reader::read_image(s, OverlapPolicy::Error).is_err());
+/// ```
+pub fn read_image(s: &str, policy: OverlapPolicy) -> Result<Image, crate::Error> {
+    let mut image = Image::new();
+
+    for LocatedRecord { line, record, .. } in read_records_located(s) {
+        let record = record.map_err(|error| crate::Error::Syntax { line, error })?;
+
+        let (address, data) = match record.memory_effect() {
+            MemoryEffect::Write { address, data } => (address, data),
+            _ => continue,
+        };
+
+        if address as u64 + data.len() as u64 > 1 << 32 {
+            return Err(crate::Error::AddressRangeOverflow { line, address });
+        }
+
+        match policy {
+            OverlapPolicy::Overwrite => image.write(address, data),
+            OverlapPolicy::KeepFirst => {
+                for (gap, len) in image.gaps(address, data.len()) {
+                    let offset = (gap - address) as usize;
+                    image.write(gap, &data[offset..offset + len]);
+                }
+            }
+            OverlapPolicy::Error => {
+                let mut at = address as u64;
+                for (gap, len) in image.gaps(address, data.len()) {
+                    if gap as u64 != at {
+                        break;
+                    }
+                    at += len as u64;
+                }
+
+                if at < address as u64 + data.len() as u64 {
+                    return Err(crate::Error::Overlap {
+                        line,
+                        address: at as u32,
+                    });
+                }
+                image.write(address, data);
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+/// What can be learnt about a line from its first few fields, without
+/// decoding its payload or verifying its checksum
+#[derive(Debug, PartialEq)]
+enum Peek {
+    /// Data record covering the half-open address range
+    Data(u64, u64),
+    /// Well-formed start of a non-data record
+    NotData,
+    /// Not enough of a record to tell
+    Malformed,
+}
+
+fn peek(s: &[u8]) -> Peek {
+    let address_len = match (s.first().map(u8::to_ascii_uppercase), s.get(1)) {
+        (Some(b'S'), Some(b'1')) => 2,
+        (Some(b'S'), Some(b'2')) => 3,
+        (Some(b'S'), Some(b'3')) => 4,
+        (Some(b'S'), Some(b'0'..=b'9')) => return Peek::NotData,
+        _ => return Peek::Malformed,
+    };
+
+    let byte_count = match byte_count_of(s) {
+        Ok(byte_count) if byte_count as usize > address_len => byte_count as usize,
+        _ => return Peek::Malformed,
+    };
+
+    let mut address = 0u64;
+    for i in 0..address_len {
+        match hex_byte_at(s, 4 + 2 * i, HexField::Payload) {
+            Ok(b) => address = address << 8 | b as u64,
+            Err(_) => return Peek::Malformed,
+        }
+    }
+
+    let data_len = (byte_count - address_len - 1) as u64;
+
+    Peek::Data(address, address + data_len)
+}
+
+/// Reads the data records which write to any address within `range` from a
+/// newline separated (either "\n" or "\r\n") string, returning an iterator
+/// over them
+///
+/// Only the type, byte count and address fields of each line are inspected
+/// before deciding whether to decode it, so extracting a small window from a
+/// very large file is cheap. Records outside the window, and all non-data
+/// records, are skipped without being decoded - errors in those lines (e.g.
+/// checksum mismatches) are not reported. Lines too malformed to determine
+/// their address are decoded in full so their error is reported.
+///
+/// Empty data records are returned if their address lies within the window.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut records = srec::reader::read_records_in_range(
+///     "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n",
+///     0x1238..0x1240,
+/// );
+///
+/// assert_eq!(
+///     records.next(),
+///     Some(Ok(srec::Record::S1(srec::Data {
+///         address: srec::Address16(0x1238),
+///         data: vec![0x04, 0x05, 0x06, 0x07],
+///     })))
+/// );
+/// assert_eq!(records.next(), None);
+/// ```
+pub fn read_records_in_range<'a>(
+    s: &'a str,
+    range: impl RangeBounds<u32>,
+) -> impl DoubleEndedIterator<Item = Result<Record, Error>> + 'a {
+    // Work with a half-open range of u64s so the end of the address space can
+    // be represented
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start as u64,
+        Bound::Excluded(&start) => start as u64 + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end as u64 + 1,
+        Bound::Excluded(&end) => end as u64,
+        Bound::Unbounded => 1 << 32,
+    };
+
+    let options = ReaderOptions::default();
+
+    CountedLines::new(s)
+        .map(move |line| options.prepare_line(line.as_bytes()))
+        .filter(|line| !line.is_empty())
+        .filter(move |line| match peek(line) {
+            Peek::Data(address, address_end) if address == address_end => {
+                start <= address && address < end
+            }
+            Peek::Data(address, address_end) => address < end && start < address_end,
+            Peek::NotData => false,
+            Peek::Malformed => true,
+        })
+        .map(Record::from_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_record_from_str_empty_str_returns_err_not_enough_data() {
+        let s = "";
+
+        let rr = RawRecord::from_str(s);
+
+        assert_eq!(rr, Err(Error::NotEnoughData));
+    }
+
+    #[test]
+    fn raw_record_from_str_first_character_invalid_returns_err_unexpected_character() {
+        let s = "D";
+
+        let rr = RawRecord::from_str(s);
+
+        assert_eq!(rr, Err(Error::UnexpectedCharacter));
+    }
+
+    #[test]
+    fn raw_record_from_str_no_type_value_returns_err_not_enough_data() {
+        let s = "S";
+
+        let rr = RawRecord::from_str(s);
+
+        assert_eq!(rr, Err(Error::NotEnoughData));
+    }
+
+    #[test]
+    fn raw_record_from_str_invalid_type_value_returns_err_invalid_record_type() {
+        let s = "Sx";
+
+        let rr = RawRecord::from_str(s);
+
+        assert_eq!(rr, Err(Error::InvalidRecordType('x')));
+    }
+
+    #[test]
+    fn raw_record_from_str_byte_count_zero_returns_err_byte_count_zero() {
+        let s = "S100";
+
+        let rr = RawRecord::from_str(s);
+
+        assert_eq!(rr, Err(Error::ByteCountZero));
+    }
+
+    #[test]
+    fn nibbles_decodes_exactly_the_hex_digits() {
+        for c in 0..=255u8 {
+            let expected = (c as char).to_digit(16).map_or(NOT_HEX, |d| d as u8);
+
+            assert_eq!(NIBBLES[c as usize], expected, "{:#04x}", c);
+        }
+    }
+
+    #[test]
+    fn raw_record_from_str_invalid_hex_character_returns_err_invalid_hex_digit() {
+        let s = "S104123400xx";
+
+        let rr = RawRecord::from_str(s);
+
+        assert_eq!(
+            rr,
+            Err(Error::InvalidHexDigit {
+                field: HexField::Checksum,
+                column: 11,
+                found: 'x'
+            })
+        );
+    }
+
+    #[test]
+    fn raw_record_from_str_byte_count_too_large_returns_err_not_enough_data() {
+        let s = "S1100000FFEF";
+
+        let rr = RawRecord::from_str(s);
+
+        assert_eq!(rr, Err(Error::NotEnoughData));
+    }
+
+    #[test]
+    fn raw_record_from_str_valid_record_empty_returns_ok_correct_raw_record() {
+        let s = "S101FE";
+
+        let rr = RawRecord::from_str(s);
+
+        assert_eq!(
+            rr,
+            Ok(RawRecord {
+                t: 1,
+                bytes: vec![]
+            })
+        );
+    }
+
+    #[test]
+    fn raw_record_from_str_valid_record_valid_checksum_returns_ok_correct_raw_record() {
+        let s = "S1101234000102030405060708090A0B0C5B";
+
+        let rr = RawRecord::from_str(s);
+
+        assert_eq!(
+            rr,
+            Ok(RawRecord {
+                t: 1,
+                bytes: vec![
+                    0x12, 0x34, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a,
+                    0x0b, 0x0c
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn raw_record_from_str_valid_record_invalid_checksum_returns_ok_correct_raw_record() {
+        let s = "S1101234000102030405060708090A0B0CFF";
+
+        let rr = RawRecord::from_str(s);
+
+        assert_eq!(
+            rr,
+            Err(Error::ChecksumMismatch {
+                t: 1,
+                address: Some(0x1234),
+                found: 0xff,
+                computed: 0x5b,
+            })
+        );
+    }
+
+    #[test]
+    fn raw_record_from_str_maximum_length_record_returns_ok_correct_raw_record() {
+        let mut s = String::from("S1FF");
+        s.push_str(&"00".repeat(255));
+
+        let rr = RawRecord::from_str(&s);
+
+        assert_eq!(
+            rr,
+            Ok(RawRecord {
+                t: 1,
+                bytes: vec![0x00; 254]
+            })
+        );
+    }
+
+    #[test]
+    fn raw_record_from_str_non_ascii_character_returns_err_non_ascii_character() {
+        let s = "S1\u{e9}";
+
+        let rr = RawRecord::from_str(s);
+
+        assert_eq!(rr, Err(Error::NonAsciiCharacter));
+    }
+
+    #[test]
+    fn s0_empty_string_from_str_returns_correct_record() {
+        let s = "S0030000FC";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Ok(Record::S0("".into())));
+    }
+
+    #[test]
+    fn s0_simple_string_from_str_returns_correct_record() {
+        let s = "S00600004844521B";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Ok(Record::S0("HDR".into())));
+    }
+
+    #[test]
+    fn s0_null_terminated_string_from_str_returns_correct_record() {
+        let s = "S009000048445200000018";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Ok(Record::S0("HDR".into())));
+    }
+
+    #[test]
+    fn s0_invalid_utf8_from_str_returns_err_invalid_header_encoding() {
+        let s = "S0060000FF48446E";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Err(Error::InvalidHeaderEncoding(vec![0xff, 0x48, 0x44])));
+    }
+
+    #[test]
+    fn s0_invalid_utf8_parse_spanned_reports_header_data_span() {
+        let s = "S0060000FF48446E";
+
+        let r = Record::parse_spanned(s);
+
+        assert_eq!(
+            r,
+            Err(SpannedError {
+                error: Error::InvalidHeaderEncoding(vec![0xff, 0x48, 0x44]),
+                span: 8..14
+            })
+        );
+    }
+
+    #[test]
+    fn s0_invalid_utf8_with_trailing_text_parse_spanned_reports_header_data_span() {
+        for s in &["S0060000FF48446E\r", "S0060000FF48446E ; header"] {
+            let r = Record::parse_spanned(s);
+
+            assert_eq!(
+                r,
+                Err(SpannedError {
+                    error: Error::InvalidHeaderEncoding(vec![0xff, 0x48, 0x44]),
+                    span: 8..14
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn s0_invalid_from_str_returns_err_not_enough_data() {
+        let s = "S00212EB";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Err(Error::NotEnoughData));
+    }
+
+    #[test]
+    fn s1_empty_from_str_returns_correct_record() {
+        let s = "S1031234B6";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(
+            r,
+            Ok(Record::S1(Data {
+                address: Address16(0x1234),
+                data: vec![]
+            }))
+        );
+    }
+
+    #[test]
+    fn s1_with_data_from_str_returns_correct_record() {
+        let s = "S107123400010203AC";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(
+            r,
+            Ok(Record::S1(Data {
+                address: Address16(0x1234),
+                data: vec![0x00, 0x01, 0x02, 0x03]
+            }))
+        );
+    }
+
+    #[test]
+    fn s1_invalid_from_str_returns_err_not_enough_data() {
+        let s = "S10212EB";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Err(Error::NotEnoughData));
+    }
+
+    #[test]
+    fn s2_empty_from_str_returns_correct_record() {
+        let s = "S2041234565F";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(
+            r,
+            Ok(Record::S2(Data {
+                address: Address24(0x123456),
+                data: vec![]
+            }))
+        );
+    }
+
+    #[test]
+    fn s2_with_data_from_str_returns_correct_record() {
+        let s = "S2081234560001020355";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(
+            r,
+            Ok(Record::S2(Data {
+                address: Address24(0x123456),
+                data: vec![0x00, 0x01, 0x02, 0x03]
+            }))
+        );
+    }
+
+    #[test]
+    fn s2_invalid_from_str_returns_err_not_enough_data() {
+        let s = "S2031234B6";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Err(Error::NotEnoughData));
+    }
+
+    #[test]
+    fn s3_empty_from_str_returns_correct_record() {
+        let s = "S30512345678E6";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(
+            r,
+            Ok(Record::S3(Data {
+                address: Address32(0x12345678),
+                data: vec![]
+            }))
+        );
+    }
+
+    #[test]
+    fn s3_with_data_from_str_returns_correct_record() {
+        let s = "S3091234567800010203DC";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(
+            r,
+            Ok(Record::S3(Data {
+                address: Address32(0x12345678),
+                data: vec![0x00, 0x01, 0x02, 0x03]
+            }))
+        );
+    }
+
+    #[test]
+    fn s3_invalid_from_str_returns_err_not_enough_data() {
+        let s = "S3041234565F";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Err(Error::NotEnoughData));
+    }
+
+    #[test]
+    fn s5_returns_correct_record() {
+        let s = "S5031234B6";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Ok(Record::S5(Count16(0x1234))));
+    }
+
+    #[test]
+    fn s5_invalid_from_str_returns_err_not_enough_data() {
+        let s = "S50212EB";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Err(Error::NotEnoughData));
+    }
+
+    #[test]
+    fn s6_returns_correct_record() {
+        let s = "S6041234565F";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Ok(Record::S6(Count24(0x123456))));
+    }
+
+    #[test]
+    fn s6_invalid_from_str_returns_err_not_enough_data() {
+        let s = "S6031234B6";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Err(Error::NotEnoughData));
+    }
+
+    #[test]
+    fn s7_returns_correct_record() {
+        let s = "S70512345678E6";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Ok(Record::S7(Address32(0x12345678))));
+    }
+
+    #[test]
+    fn s7_invalid_from_str_returns_err_not_enough_data() {
+        let s = "S7041234565F";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Err(Error::NotEnoughData));
+    }
+
+    #[test]
+    fn s8_returns_correct_record() {
+        let s = "S8041234565F";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Ok(Record::S8(Address24(0x123456))));
+    }
+
+    #[test]
+    fn s8_invalid_from_str_returns_err_not_enough_data() {
+        let s = "S8031234B6";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Err(Error::NotEnoughData));
+    }
+
+    #[test]
+    fn s9_returns_correct_record() {
+        let s = "S9031234B6";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Ok(Record::S9(Address16(0x1234))));
+    }
+
+    #[test]
+    fn s9_invalid_from_str_returns_err_not_enough_data() {
+        let s = "S90212EB";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Err(Error::NotEnoughData));
+    }
+
+    #[test]
+    fn record_from_str_returns_err_invalid_record_type_on_unknown_type() {
+        let s = "S401FE";
+
+        let r = s.parse::<Record>();
+
+        assert_eq!(r, Err(Error::InvalidRecordType('4')));
+    }
+
+    #[test]
+    fn read_records_empty_string_returns_empty_iterator() {
+        let s = "";
+
+        let mut ri = read_records(s);
+
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_one_line_returns_iterator_with_one_item() {
         let s = "S00600004844521B";
 
-        let r = s.parse::<Record>();
+        let mut ri = read_records(s);
+
+        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_one_line_with_trailing_newline_returns_iterator_with_one_item() {
+        let s = "S00600004844521B\n";
+
+        let mut ri = read_records(s);
+
+        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_one_line_with_empty_line_returns_iterator_with_one_item() {
+        let s = "S00600004844521B\n\n";
+
+        let mut ri = read_records(s);
+
+        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_multiple_lines_returns_iterator_containing_all() {
+        let s = "S00600004844521B\nS107123400010203AC";
+
+        let mut ri = read_records(s);
+
+        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
+        assert_eq!(
+            ri.next(),
+            Some(Ok(Record::S1(Data {
+                address: Address16(0x1234),
+                data: vec![0x00, 0x01, 0x02, 0x03],
+            })))
+        );
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_multiple_lines_reversed_returns_iterator_containing_all_in_reverse() {
+        let s = "S00600004844521B\nS107123400010203AC";
+
+        let mut ri = read_records(s).rev();
+
+        assert_eq!(
+            ri.next(),
+            Some(Ok(Record::S1(Data {
+                address: Address16(0x1234),
+                data: vec![0x00, 0x01, 0x02, 0x03],
+            })))
+        );
+        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_size_hint_is_bounded_by_line_count() {
+        let s = "S00600004844521B\n\nS107123400010203AC\n";
+
+        let mut ri = read_records(s);
+
+        assert_eq!(ri.size_hint(), (0, Some(3)));
+        ri.next();
+        assert_eq!(ri.size_hint(), (0, Some(2)));
+    }
+
+    #[test]
+    fn read_records_with_skip_line_prefix_strips_indentation_and_timestamps() {
+        let s = "\tS00600004844521B\n12:00:01.5 > S107123400010203AC\n";
+        let options = ReaderOptions {
+            skip_line_prefix: true,
+            ..Default::default()
+        };
+
+        let mut ri = read_records_with(s, &options);
+
+        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
+        assert_eq!(
+            ri.next(),
+            Some(Ok(Record::S1(Data {
+                address: Address16(0x1234),
+                data: vec![0x00, 0x01, 0x02, 0x03],
+            })))
+        );
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_with_skip_line_prefix_strips_prefix_of_lowercase_record() {
+        let s = "12:00:01.5 > s9031234b6\n";
+        let options = ReaderOptions {
+            skip_line_prefix: true,
+            ..Default::default()
+        };
+
+        let mut ri = read_records_with(s, &options);
+
+        assert_eq!(ri.next(), Some(Ok(Record::S9(Address16(0x1234)))));
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_with_skip_line_prefix_line_without_s_returns_err_unexpected_character() {
+        let s = "12:00:01.5 > garbage";
+        let options = ReaderOptions {
+            skip_line_prefix: true,
+            ..Default::default()
+        };
+
+        let mut ri = read_records_with(s, &options);
+
+        assert_eq!(ri.next(), Some(Err(Error::UnexpectedCharacter)));
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_without_skip_line_prefix_returns_err_unexpected_character() {
+        let s = "12:00:01.5 > S00600004844521B";
+
+        let mut ri = read_records(s);
+
+        assert_eq!(ri.next(), Some(Err(Error::UnexpectedCharacter)));
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn peek_data_record_returns_address_range() {
+        assert_eq!(peek(b"S107123400010203AC"), Peek::Data(0x1234, 0x1238));
+        assert_eq!(
+            peek(b"S2081234560001020355"),
+            Peek::Data(0x123456, 0x12345a)
+        );
+        assert_eq!(peek(b"S30512345678E6"), Peek::Data(0x12345678, 0x12345678));
+    }
+
+    #[test]
+    fn peek_non_data_record_returns_not_data() {
+        assert_eq!(peek(b"S00600004844521B"), Peek::NotData);
+        assert_eq!(peek(b"S9031234B6"), Peek::NotData);
+    }
+
+    #[test]
+    fn peek_truncated_record_returns_malformed() {
+        assert_eq!(peek(b"S1"), Peek::Malformed);
+        assert_eq!(peek(b"S10212"), Peek::Malformed);
+        assert_eq!(peek(b"S10712"), Peek::Malformed);
+        assert_eq!(peek(b"X107123400010203AC"), Peek::Malformed);
+    }
+
+    #[test]
+    fn read_records_in_range_returns_only_intersecting_data_records() {
+        let s = "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n";
+
+        let mut ri = read_records_in_range(s, 0x1237..=0x1237);
+
+        assert_eq!(
+            ri.next(),
+            Some(Ok(Record::S1(Data {
+                address: Address16(0x1234),
+                data: vec![0x00, 0x01, 0x02, 0x03],
+            })))
+        );
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_in_range_window_ending_at_record_start_returns_nothing() {
+        let s = "S107123400010203AC";
+
+        let mut ri = read_records_in_range(s, ..0x1234);
+
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_in_range_skips_errors_outside_window() {
+        let s = "S107123400010203FF\nS10712380405060798";
+
+        let mut ri = read_records_in_range(s, 0x1238..);
+
+        assert_eq!(
+            ri.next(),
+            Some(Ok(Record::S1(Data {
+                address: Address16(0x1238),
+                data: vec![0x04, 0x05, 0x06, 0x07],
+            })))
+        );
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_in_range_reports_errors_inside_window() {
+        let s = "S107123400010203FF\nS1";
+
+        let mut ri = read_records_in_range(s, ..);
+
+        assert_eq!(
+            ri.next(),
+            Some(Err(Error::ChecksumMismatch {
+                t: 1,
+                address: Some(0x1234),
+                found: 0xff,
+                computed: 0xac,
+            }))
+        );
+        assert_eq!(ri.next(), Some(Err(Error::NotEnoughData)));
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_in_range_includes_end_of_address_space() {
+        let s = "S305FFFFFFFFFE";
+
+        let mut ri = read_records_in_range(s, 0xffffffff..=0xffffffff);
+
+        assert_eq!(
+            ri.next(),
+            Some(Ok(Record::S3(Data {
+                address: Address32(0xffffffff),
+                data: vec![],
+            })))
+        );
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn raw_record_parse_strict_byte_count_below_minimum_returns_err_byte_count_too_small() {
+        let options = ReaderOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        for s in &[
+            "S00212",
+            "S10212",
+            "S2031234",
+            "S304123456",
+            "S50212",
+            "S6031234",
+            "S704123456",
+            "S8031234",
+            "S90212",
+        ] {
+            let rr = RawRecord::parse(s.as_bytes(), &options);
+
+            assert_eq!(rr, Err(Error::ByteCountTooSmall), "{}", s);
+        }
+    }
+
+    #[test]
+    fn raw_record_parse_strict_byte_count_at_minimum_returns_ok() {
+        let options = ReaderOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        let rr = RawRecord::parse(b"S30512345678E6", &options);
+
+        assert_eq!(
+            rr,
+            Ok(RawRecord {
+                t: 3,
+                bytes: vec![0x12, 0x34, 0x56, 0x78],
+            })
+        );
+    }
+
+    #[test]
+    fn raw_record_parse_strict_unknown_type_does_not_check_byte_count() {
+        let options = ReaderOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        let rr = RawRecord::parse(b"S401FE", &options);
+
+        assert_eq!(
+            rr,
+            Ok(RawRecord {
+                t: 4,
+                bytes: vec![]
+            })
+        );
+    }
+
+    #[test]
+    fn read_records_with_strict_reports_byte_count_too_small() {
+        let s = "S3041234565F";
+        let options = ReaderOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        let mut ri = read_records_with(s, &options);
+
+        assert_eq!(ri.next(), Some(Err(Error::ByteCountTooSmall)));
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_with_skip_keepalives_skips_checksum_only_records() {
+        let s = "S00600004844521B\nS501FE\nS107123400010203AC\nS101FE\n";
+        let options = ReaderOptions {
+            skip_keepalives: true,
+            ..Default::default()
+        };
+
+        let mut ri = read_records_with(s, &options);
+
+        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
+        assert_eq!(
+            ri.next(),
+            Some(Ok(Record::S1(Data {
+                address: Address16(0x1234),
+                data: vec![0x00, 0x01, 0x02, 0x03],
+            })))
+        );
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_with_skip_keepalives_reports_keepalive_with_bad_checksum() {
+        let s = "S501FF";
+        let options = ReaderOptions {
+            skip_keepalives: true,
+            ..Default::default()
+        };
+
+        let mut ri = read_records_with(s, &options);
+
+        assert_eq!(
+            ri.next(),
+            Some(Err(Error::ChecksumMismatch {
+                t: 5,
+                address: None,
+                found: 0xff,
+                computed: 0xfe,
+            }))
+        );
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn read_records_without_skip_keepalives_reports_keepalive() {
+        let s = "S501FE";
+
+        let mut ri = read_records(s);
+
+        assert_eq!(ri.next(), Some(Err(Error::NotEnoughData)));
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn record_from_bytes_non_ascii_byte_returns_err_non_ascii_character() {
+        let s = b"S107\xc3\xa93400010203AC";
+
+        let r = Record::from_bytes(s);
+
+        assert_eq!(r, Err(Error::NonAsciiCharacter));
+    }
+
+    #[test]
+    fn record_from_bytes_invalid_ascii_character_returns_err_invalid_hex_digit() {
+        let s = b"S107123400010203AX";
+
+        let r = Record::from_bytes(s);
+
+        assert_eq!(
+            r,
+            Err(Error::InvalidHexDigit {
+                field: HexField::Checksum,
+                column: 18,
+                found: 'X'
+            })
+        );
+    }
+
+    #[test]
+    fn read_records_from_bytes_returns_same_records_as_read_records() {
+        let s =
+            "S00600004844521B\r\n\r\nS107123400010203AC\r\nS10712380405060798\r\nS9031234B6\r\n";
 
-        assert_eq!(r, Ok(Record::S0("HDR".into())));
+        let from_bytes = read_records_from_bytes(s.as_bytes()).collect::<Vec<_>>();
+        let from_str = read_records(s).collect::<Vec<_>>();
+
+        assert_eq!(from_bytes, from_str);
     }
 
     #[test]
-    fn s0_null_terminated_string_from_str_returns_correct_record() {
-        let s = "S009000048445200000018";
+    fn read_records_from_bytes_utf16_returns_single_err_unsupported_encoding() {
+        let utf16 = |s: &str, le: bool| {
+            s.encode_utf16()
+                .flat_map(|c| if le { c.to_le_bytes() } else { c.to_be_bytes() })
+                .collect::<Vec<_>>()
+        };
+        let cases = [
+            (utf16("\u{feff}S9031234B6\r\n", true), Encoding::Utf16Le),
+            (utf16("\u{feff}S9031234B6\r\n", false), Encoding::Utf16Be),
+            (
+                utf16("S9031234B6\r\nS9031234B6\r\n", true),
+                Encoding::Utf16Le,
+            ),
+            (
+                utf16("S9031234B6\r\nS9031234B6\r\n", false),
+                Encoding::Utf16Be,
+            ),
+        ];
 
-        let r = s.parse::<Record>();
+        for (s, encoding) in &cases {
+            let records = read_records_from_bytes(s).collect::<Vec<_>>();
 
-        assert_eq!(r, Ok(Record::S0("HDR".into())));
+            assert_eq!(
+                records,
+                [Err(Error::UnsupportedEncoding(*encoding))],
+                "{:?}",
+                s
+            );
+        }
     }
 
     #[test]
-    fn s1_empty_from_str_returns_correct_record() {
-        let s = "S1031234B6";
+    fn read_records_from_bytes_invalid_utf8_reports_error_in_affected_record_only() {
+        let s = b"S00600004844521B\nS\xff\nS9031234B6";
+
+        let mut ri = read_records_from_bytes(s).rev();
+
+        assert_eq!(ri.next(), Some(Ok(Record::S9(Address16(0x1234)))));
+        assert_eq!(ri.next(), Some(Err(Error::NonAsciiCharacter)));
+        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn record_parse_spanned_reports_span_of_failing_field() {
+        let cases: &[(&str, Error, Range<usize>)] = &[
+            ("", Error::NotEnoughData, 0..0),
+            ("X1", Error::UnexpectedCharacter, 0..1),
+            ("SX", Error::InvalidRecordType('X'), 1..2),
+            ("S1", Error::NotEnoughData, 2..2),
+            ("S10", Error::NotEnoughData, 2..3),
+            ("S1G7", Error::InvalidByteCount, 2..4),
+            ("S100", Error::ByteCountZero, 2..4),
+            (
+                "S107123400Z10203AC",
+                Error::InvalidHexDigit {
+                    field: HexField::Payload,
+                    column: 11,
+                    found: 'Z',
+                },
+                10..12,
+            ),
+            ("S1071234000102", Error::NotEnoughData, 14..14),
+            (
+                "S107123400010203FF",
+                Error::ChecksumMismatch {
+                    t: 1,
+                    address: Some(0x1234),
+                    found: 0xff,
+                    computed: 0xac,
+                },
+                16..18,
+            ),
+            ("S504000001FA", Error::NotEnoughData, 2..4),
+            ("S4030000FC", Error::InvalidRecordType('4'), 1..2),
+        ];
+
+        for (s, error, span) in cases {
+            let r = Record::parse_spanned(s);
+
+            assert_eq!(
+                r,
+                Err(SpannedError {
+                    error: error.clone(),
+                    span: span.clone()
+                }),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn record_parse_spanned_valid_record_returns_record() {
+        let s = "S9031234B6";
+
+        let r = Record::parse_spanned(s);
+
+        assert_eq!(r, Ok(Record::S9(Address16(0x1234))));
+    }
+
+    #[test]
+    fn read_records_lossy_separates_records_and_errors_with_line_numbers() {
+        let s = "S00600004844521B\r\n\r\nS1071234000102\r\nS107123400010203AC\r\nS9031234B7\r\n";
+
+        let (records, errors) = read_records_lossy(s);
+
+        assert_eq!(
+            records,
+            [
+                Record::S0("HDR".into()),
+                Record::S1(Data {
+                    address: Address16(0x1234),
+                    data: vec![0x00, 0x01, 0x02, 0x03],
+                }),
+            ]
+        );
+        assert_eq!(
+            errors,
+            [
+                (3, Error::NotEnoughData),
+                (
+                    5,
+                    Error::ChecksumMismatch {
+                        t: 9,
+                        address: Some(0x1234),
+                        found: 0xb7,
+                        computed: 0xb6,
+                    }
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn read_records_lossy_valid_file_returns_no_errors() {
+        let s = "S00600004844521B\nS107123400010203AC\nS9031234B6\n";
+
+        let (records, errors) = read_records_lossy(s);
+
+        assert_eq!(Ok(records), read_records(s).collect::<Result<Vec<_>, _>>());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn record_from_str_lowercase_tag_and_hex_returns_record() {
+        let s = "s107123400010203ac";
 
         let r = s.parse::<Record>();
 
@@ -408,265 +2711,505 @@ mod tests {
             r,
             Ok(Record::S1(Data {
                 address: Address16(0x1234),
-                data: vec![]
+                data: vec![0x00, 0x01, 0x02, 0x03],
             }))
         );
     }
 
     #[test]
-    fn s1_with_data_from_str_returns_correct_record() {
-        let s = "S107123400010203AC";
+    fn read_records_in_range_lowercase_tag_returns_record() {
+        let s = "s00600004844521b\ns107123400010203ac\ns9031234b6\n";
 
-        let r = s.parse::<Record>();
+        let mut ri = read_records_in_range(s, 0x1234..0x1235);
 
         assert_eq!(
-            r,
-            Ok(Record::S1(Data {
+            ri.next(),
+            Some(Ok(Record::S1(Data {
                 address: Address16(0x1234),
-                data: vec![0x00, 0x01, 0x02, 0x03]
-            }))
+                data: vec![0x00, 0x01, 0x02, 0x03],
+            })))
         );
+        assert_eq!(ri.next(), None);
     }
 
     #[test]
-    fn s1_invalid_from_str_returns_err_not_enough_data() {
-        let s = "S10212EB";
+    fn read_records_located_trailing_text_returns_comment() {
+        let s = "S9031234B6  ; reset vector\r\nS9031234B6 \r\n";
 
-        let r = s.parse::<Record>();
+        let located = read_records_located(s).collect::<Vec<_>>();
 
-        assert_eq!(r, Err(Error::NotEnoughData));
+        assert_eq!(located[0].offset, 0..10);
+        assert_eq!(located[0].comment.as_deref(), Some("; reset vector"));
+        assert_eq!(located[0].record, Ok(Record::S9(Address16(0x1234))));
+        assert_eq!(located[1].comment, None);
     }
 
     #[test]
-    fn s2_empty_from_str_returns_correct_record() {
-        let s = "S2041234565F";
+    fn read_records_located_reports_line_and_offset_of_each_record() {
+        let s = "S00600004844521B\n\n\tS107123400010203FF \nS9031234B6";
 
-        let r = s.parse::<Record>();
+        let located = read_records_located(s).collect::<Vec<_>>();
 
         assert_eq!(
-            r,
-            Ok(Record::S2(Data {
-                address: Address24(0x123456),
-                data: vec![]
-            }))
+            located,
+            [
+                LocatedRecord {
+                    line: 1,
+                    offset: 0..16,
+                    record: Ok(Record::S0("HDR".into())),
+                    comment: None,
+                },
+                LocatedRecord {
+                    line: 3,
+                    offset: 19..37,
+                    record: Err(Error::ChecksumMismatch {
+                        t: 1,
+                        address: Some(0x1234),
+                        found: 0xff,
+                        computed: 0xac,
+                    }),
+                    comment: None,
+                },
+                LocatedRecord {
+                    line: 4,
+                    offset: 39..49,
+                    record: Ok(Record::S9(Address16(0x1234))),
+                    comment: None,
+                },
+            ]
         );
     }
 
     #[test]
-    fn s2_with_data_from_str_returns_correct_record() {
-        let s = "S2081234560001020355";
+    fn read_records_located_offsets_index_input() {
+        let s = "S00600004844521B\r\nS107123400010203AC\r\nS9031234B6\r\n";
 
-        let r = s.parse::<Record>();
+        for located in read_records_located(s) {
+            let text = &s[located.offset];
+
+            assert_eq!(text.parse::<Record>(), located.record);
+        }
+    }
+
+    #[test]
+    fn read_records_with_ignore_checksums_accepts_bad_checksum() {
+        let options = ReaderOptions {
+            ignore_checksums: true,
+            ..Default::default()
+        };
+        let s = "S107123400010203FF";
+
+        let mut ri = read_records_with(s, &options);
 
         assert_eq!(
-            r,
-            Ok(Record::S2(Data {
-                address: Address24(0x123456),
-                data: vec![0x00, 0x01, 0x02, 0x03]
-            }))
+            ri.next(),
+            Some(Ok(Record::S1(Data {
+                address: Address16(0x1234),
+                data: vec![0x00, 0x01, 0x02, 0x03],
+            })))
         );
+        assert_eq!(ri.next(), None);
     }
 
     #[test]
-    fn s2_invalid_from_str_returns_err_not_enough_data() {
-        let s = "S2031234B6";
+    fn record_parse_case_sensitive_lowercase_returns_err() {
+        let options = ReaderOptions {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        let cases = [
+            ("s9031234B6", Error::UnexpectedCharacter, 0..1),
+            (
+                "S9031234b6",
+                Error::InvalidHexDigit {
+                    field: HexField::Checksum,
+                    column: 9,
+                    found: 'b',
+                },
+                8..10,
+            ),
+        ];
+
+        for (s, error, span) in &cases {
+            let r = Record::parse_spanned_with(s.as_bytes(), &options);
+
+            assert_eq!(
+                r,
+                Err(SpannedError {
+                    error: error.clone(),
+                    span: span.clone()
+                }),
+                "{}",
+                s
+            );
+        }
+    }
 
-        let r = s.parse::<Record>();
+    #[test]
+    fn read_records_with_case_sensitive_and_max_line_length_ignore_comment() {
+        let options = ReaderOptions {
+            case_sensitive: true,
+            max_line_length: Some(10),
+            ..Default::default()
+        };
+        let s = "S9031234B6  ; reset vector\n";
 
-        assert_eq!(r, Err(Error::NotEnoughData));
+        let mut ri = read_records_with(s, &options);
+
+        assert_eq!(ri.next(), Some(Ok(Record::S9(Address16(0x1234)))));
+        assert_eq!(ri.next(), None);
     }
 
     #[test]
-    fn s3_empty_from_str_returns_correct_record() {
-        let s = "S30512345678E6";
+    fn read_records_with_max_line_length_reports_long_lines() {
+        let options = ReaderOptions {
+            max_line_length: Some(16),
+            ..Default::default()
+        };
+        let s = "S00600004844521B\nS107123400010203AC\n";
 
-        let r = s.parse::<Record>();
+        let mut ri = read_records_with(s, &options);
+
+        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
+        assert_eq!(ri.next(), Some(Err(Error::LineTooLong)));
+        assert_eq!(ri.next(), None);
+    }
 
+    #[test]
+    fn reader_options_only_bounded_limits_line_length() {
+        assert_eq!(ReaderOptions::default().max_line_length, None);
         assert_eq!(
-            r,
-            Ok(Record::S3(Data {
-                address: Address32(0x12345678),
-                data: vec![]
-            }))
+            ReaderOptions::bounded().max_line_length,
+            Some(DEFAULT_MAX_LINE_LENGTH)
         );
     }
 
     #[test]
-    fn s3_with_data_from_str_returns_correct_record() {
-        let s = "S3091234567800010203DC";
+    fn read_records_with_skip_unknown_records_skips_s4() {
+        let options = ReaderOptions {
+            unknown_records: UnknownRecords::Skip,
+            ..Default::default()
+        };
+        let s = "S4030000FC\nS9031234B6\n";
 
-        let r = s.parse::<Record>();
+        let mut ri = read_records_with(s, &options);
 
+        assert_eq!(ri.next(), Some(Ok(Record::S9(Address16(0x1234)))));
+        assert_eq!(ri.next(), None);
         assert_eq!(
-            r,
-            Ok(Record::S3(Data {
-                address: Address32(0x12345678),
-                data: vec![0x00, 0x01, 0x02, 0x03]
-            }))
+            read_records(s).next(),
+            Some(Err(Error::InvalidRecordType('4')))
         );
     }
 
     #[test]
-    fn s3_invalid_from_str_returns_err_not_enough_data() {
-        let s = "S3041234565F";
+    fn read_records_with_report_truncation_reports_cut_off_final_line() {
+        let options = ReaderOptions {
+            report_truncation: true,
+            ..Default::default()
+        };
+        let s = "S1071234000102\nS00600004844521B\nS1071234000102";
 
-        let r = s.parse::<Record>();
+        let records = read_records_with(s, &options).collect::<Vec<_>>();
 
-        assert_eq!(r, Err(Error::NotEnoughData));
+        assert_eq!(
+            records,
+            [
+                Err(Error::NotEnoughData),
+                Ok(Record::S0("HDR".into())),
+                Err(Error::TruncatedFinalRecord("S1071234000102".into())),
+            ]
+        );
     }
 
     #[test]
-    fn s5_returns_correct_record() {
-        let s = "S5031234B6";
+    fn read_records_with_report_truncation_terminated_final_line_is_not_truncated() {
+        let options = ReaderOptions {
+            report_truncation: true,
+            ..Default::default()
+        };
+        let s = "S00600004844521B\r\nS1071234000102\r\n";
 
-        let r = s.parse::<Record>();
+        let mut ri = read_records_with(s, &options).rev();
 
-        assert_eq!(r, Ok(Record::S5(Count16(0x1234))));
+        assert_eq!(ri.next(), Some(Err(Error::NotEnoughData)));
     }
 
     #[test]
-    fn s5_invalid_from_str_returns_err_not_enough_data() {
-        let s = "S50212EB";
+    fn read_records_without_report_truncation_returns_err_not_enough_data() {
+        let s = "S00600004844521B\nS1071234000102";
 
-        let r = s.parse::<Record>();
+        let mut ri = read_records(s).rev();
 
-        assert_eq!(r, Err(Error::NotEnoughData));
+        assert_eq!(ri.next(), Some(Err(Error::NotEnoughData)));
     }
 
     #[test]
-    fn s6_returns_correct_record() {
-        let s = "S6041234565F";
+    fn read_records_with_keep_unknown_records_returns_unknown() {
+        let options = ReaderOptions {
+            unknown_records: UnknownRecords::Keep,
+            ..Default::default()
+        };
+        let s = "S4030000FC\nS4030000FF\nS9031234B6\n";
 
-        let r = s.parse::<Record>();
+        let mut ri = read_records_with(s, &options);
 
-        assert_eq!(r, Ok(Record::S6(Count24(0x123456))));
+        assert_eq!(
+            ri.next(),
+            Some(Ok(Record::Unknown {
+                t: 4,
+                bytes: vec![0x00, 0x00],
+            }))
+        );
+        assert_eq!(
+            ri.next(),
+            Some(Err(Error::ChecksumMismatch {
+                t: 4,
+                address: None,
+                found: 0xff,
+                computed: 0xfc,
+            }))
+        );
+        assert_eq!(ri.next(), Some(Ok(Record::S9(Address16(0x1234)))));
+        assert_eq!(ri.next(), None);
     }
 
     #[test]
-    fn s6_invalid_from_str_returns_err_not_enough_data() {
-        let s = "S6031234B6";
+    fn record_parse_strict_s0_nonzero_address_returns_err_non_zero_header_address() {
+        let options = ReaderOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let s = "S0061234484452D5";
 
-        let r = s.parse::<Record>();
+        let r = Record::parse_spanned_with(s.as_bytes(), &options);
 
-        assert_eq!(r, Err(Error::NotEnoughData));
+        assert_eq!(
+            r,
+            Err(SpannedError {
+                error: Error::NonZeroHeaderAddress,
+                span: 4..8
+            })
+        );
+        assert_eq!(s.parse::<Record>(), Ok(Record::S0("HDR".into())));
     }
 
     #[test]
-    fn s7_returns_correct_record() {
-        let s = "S70512345678E6";
+    fn read_records_from_reader_unterminated_final_line_returns_record() {
+        let s = "S00600004844521B\r\n\r\nS9031234B6";
 
-        let r = s.parse::<Record>();
+        let records = read_records_from_reader(s.as_bytes())
+            .map(Result::ok)
+            .collect::<Vec<_>>();
 
-        assert_eq!(r, Ok(Record::S7(Address32(0x12345678))));
+        assert_eq!(
+            records,
+            [
+                Some(Record::S0("HDR".into())),
+                Some(Record::S9(Address16(0x1234))),
+            ]
+        );
     }
 
     #[test]
-    fn s7_invalid_from_str_returns_err_not_enough_data() {
-        let s = "S7041234565F";
-
-        let r = s.parse::<Record>();
-
-        assert_eq!(r, Err(Error::NotEnoughData));
+    fn read_records_from_reader_long_line_returns_err_without_buffering_it() {
+        let s = format!("S1{}\nS9031234B6\n", "0".repeat(100_000));
+
+        let mut records = read_records_from_reader(s.as_bytes());
+        let first = records.next();
+        let capacity = records.line.capacity();
+        let second = records.next();
+
+        assert!(matches!(
+            first,
+            Some(Err(crate::Error::Syntax {
+                line: 1,
+                error: Error::LineTooLong
+            }))
+        ));
+        assert!(capacity <= 2 * DEFAULT_MAX_LINE_LENGTH);
+        assert!(matches!(second, Some(Ok(Record::S9(Address16(0x1234))))));
     }
 
     #[test]
-    fn s8_returns_correct_record() {
-        let s = "S8041234565F";
+    fn read_records_from_reader_with_max_line_length_allows_line_ending() {
+        let s = "S9031234B6\r\nS107123400010203AC\r\n";
+        let options = ReaderOptions {
+            max_line_length: Some(10),
+            ..Default::default()
+        };
 
-        let r = s.parse::<Record>();
+        let records = read_records_from_reader_with(s.as_bytes(), &options)
+            .map(|r| r.map_err(|err| err.to_string()))
+            .collect::<Vec<_>>();
 
-        assert_eq!(r, Ok(Record::S8(Address24(0x123456))));
+        assert_eq!(
+            records,
+            [
+                Ok(Record::S9(Address16(0x1234))),
+                Err("line 2: line too long".into())
+            ]
+        );
     }
 
     #[test]
-    fn s8_invalid_from_str_returns_err_not_enough_data() {
-        let s = "S8031234B6";
-
-        let r = s.parse::<Record>();
+    fn read_records_from_path_missing_file_returns_err() {
+        let r = read_records_from_path("does/not/exist.mot");
 
-        assert_eq!(r, Err(Error::NotEnoughData));
+        assert_eq!(r.unwrap_err().kind(), io::ErrorKind::NotFound);
     }
 
     #[test]
-    fn s9_returns_correct_record() {
-        let s = "S9031234B6";
+    fn s0_invalid_utf8_display_shows_header_lossily() {
+        let err = "S0060000FF48446E".parse::<Record>().unwrap_err();
 
-        let r = s.parse::<Record>();
+        let s = err.to_string();
 
-        assert_eq!(r, Ok(Record::S9(Address16(0x1234))));
+        assert_eq!(s, "header is not valid UTF-8: \"\u{fffd}HD\"");
     }
 
     #[test]
-    fn s9_invalid_from_str_returns_err_not_enough_data() {
-        let s = "S90212EB";
+    fn read_records_with_skip_non_records_skips_comment_lines() {
+        let s = "# generated by build.sh\nS00600004844521B\nlinking done\nS9031234B6\n";
+        let options = ReaderOptions {
+            skip_non_records: true,
+            ..Default::default()
+        };
 
-        let r = s.parse::<Record>();
+        let records = read_records_with(s, &options).collect::<Vec<_>>();
 
-        assert_eq!(r, Err(Error::NotEnoughData));
+        assert_eq!(
+            records,
+            [
+                Ok(Record::S0("HDR".into())),
+                Ok(Record::S9(Address16(0x1234))),
+            ]
+        );
     }
 
     #[test]
-    fn record_from_str_returns_err_unexpected_character_on_unknown_type() {
-        let s = "S401FE";
+    fn read_records_with_skip_line_if_skips_matching_lines() {
+        let s = "; comment\nS00600004844521B\nS9031234B6\n";
+        let options = ReaderOptions {
+            skip_line_if: Some(|line| line.starts_with(b";") || line.starts_with(b"S0")),
+            ..Default::default()
+        };
 
-        let r = s.parse::<Record>();
+        let records = read_records_with(s, &options).collect::<Vec<_>>();
 
-        assert_eq!(r, Err(Error::UnexpectedCharacter));
+        assert_eq!(records, [Ok(Record::S9(Address16(0x1234)))]);
     }
 
     #[test]
-    fn read_records_empty_string_returns_empty_iterator() {
-        let s = "";
+    fn read_modules_splits_at_headers_and_start_addresses() {
+        let s = "S107123400010203AC\nS9031234B6\nS10712380405060798\n\
+                 S00600004844521B\nS5030001FB\n";
 
-        let mut ri = read_records(s);
+        let modules = read_modules(s).unwrap();
 
-        assert_eq!(ri.next(), None);
+        assert_eq!(
+            modules,
+            [
+                Module {
+                    data: vec![Record::S1(Data {
+                        address: Address16(0x1234),
+                        data: vec![0x00, 0x01, 0x02, 0x03],
+                    })],
+                    start: Some(0x1234),
+                    ..Default::default()
+                },
+                Module {
+                    data: vec![Record::S1(Data {
+                        address: Address16(0x1238),
+                        data: vec![0x04, 0x05, 0x06, 0x07],
+                    })],
+                    ..Default::default()
+                },
+                Module {
+                    header: Some("HDR".into()),
+                    count: Some(1),
+                    ..Default::default()
+                },
+            ]
+        );
     }
 
     #[test]
-    fn read_records_one_line_returns_iterator_with_one_item() {
-        let s = "S00600004844521B";
+    fn read_modules_invalid_record_returns_err_syntax_with_line() {
+        let s = "S00600004844521B\n\nS107123400010203FF\n";
 
-        let mut ri = read_records(s);
+        let modules = read_modules(s);
 
-        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
-        assert_eq!(ri.next(), None);
+        assert!(matches!(
+            modules,
+            Err(crate::Error::Syntax {
+                line: 3,
+                error: Error::ChecksumMismatch { .. }
+            })
+        ));
     }
 
     #[test]
-    fn read_records_one_line_with_trailing_newline_returns_iterator_with_one_item() {
-        let s = "S00600004844521B\n";
+    fn read_image_applies_overlap_policy() {
+        let s = "S107123400010203AC\nS1071232A0A1A2A32E\n";
 
-        let mut ri = read_records(s);
+        let overwrite = read_image(s, OverlapPolicy::Overwrite).unwrap();
+        let keep_first = read_image(s, OverlapPolicy::KeepFirst).unwrap();
+        let error = read_image(s, OverlapPolicy::Error);
 
-        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
-        assert_eq!(ri.next(), None);
+        assert_eq!(
+            overwrite.blocks().collect::<Vec<_>>(),
+            [(0x1232, &[0xa0, 0xa1, 0xa2, 0xa3, 0x02, 0x03][..])]
+        );
+        assert_eq!(
+            keep_first.blocks().collect::<Vec<_>>(),
+            [(0x1232, &[0xa0, 0xa1, 0x00, 0x01, 0x02, 0x03][..])]
+        );
+        assert!(matches!(
+            error,
+            Err(crate::Error::Overlap {
+                line: 2,
+                address: 0x1234
+            })
+        ));
     }
 
     #[test]
-    fn read_records_one_line_with_empty_line_returns_iterator_with_one_item() {
-        let s = "S00600004844521B\n\n";
+    fn read_image_past_top_of_address_space_returns_err_address_range_overflow() {
+        let s = "S307FFFFFFFF0102F9\n";
+
+        for &policy in &[
+            OverlapPolicy::Overwrite,
+            OverlapPolicy::KeepFirst,
+            OverlapPolicy::Error,
+        ] {
+            let image = read_image(s, policy);
+
+            assert!(matches!(
+                image,
+                Err(crate::Error::AddressRangeOverflow {
+                    line: 1,
+                    address: 0xffff_ffff
+                })
+            ));
+        }
+    }
 
-        let mut ri = read_records(s);
+    #[test]
+    fn read_records_from_lines_matches_read_records() {
+        let s = "S00600004844521B\r\n\r\nS107123400010203FF\nS9031234B6";
 
-        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
-        assert_eq!(ri.next(), None);
+        let records = read_records_from_lines(s.split('\n')).collect::<Vec<_>>();
+
+        assert_eq!(records, read_records(s).collect::<Vec<_>>());
     }
 
     #[test]
-    fn read_records_multiple_lines_returns_iterator_containing_all() {
-        let s = "S00600004844521B\nS107123400010203AC";
+    fn invalid_hex_digit_display_gives_character_and_column() {
+        let err = "S1071234000102Z3AC".parse::<Record>().unwrap_err();
 
-        let mut ri = read_records(s);
+        let s = err.to_string();
 
-        assert_eq!(ri.next(), Some(Ok(Record::S0("HDR".into()))));
-        assert_eq!(
-            ri.next(),
-            Some(Ok(Record::S1(Data {
-                address: Address16(0x1234),
-                data: vec![0x00, 0x01, 0x02, 0x03],
-            })))
-        );
-        assert_eq!(ri.next(), None);
+        assert_eq!(s, "invalid hex digit 'Z' in payload at column 15");
     }
 }
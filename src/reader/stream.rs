@@ -0,0 +1,286 @@
+use super::{strip_line_ending, ReaderOptions};
+use crate::error::Error;
+use crate::record::Record;
+use futures_core::Stream;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::AsyncBufRead;
+
+/// Stream of the records read from an [`AsyncBufRead`], returned by
+/// [`read_records_async`]
+#[derive(Debug)]
+pub struct AsyncRecords<R> {
+    reader: R,
+    options: ReaderOptions,
+    cancel: Option<Arc<AtomicBool>>,
+    line: Vec<u8>,
+    too_long: bool,
+    line_number: usize,
+    done: bool,
+}
+
+impl<R> AsyncRecords<R> {
+    /// Sets a flag which cancels reading once set, returning the stream so
+    /// calls can be chained
+    ///
+    /// The flag is checked each time the stream is polled, so e.g. a GUI's
+    /// cancel button can stop reading a huge file promptly. Otherwise behaves
+    /// exactly as
+    /// [`ReadRecords::with_cancel_flag`](super::ReadRecords::with_cancel_flag).
+    pub fn with_cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Parses the line collected so far, returning `None` if it should be
+    /// skipped
+    fn take_line(&mut self) -> Option<Result<Record, Error>> {
+        self.line_number += 1;
+
+        let text = self.options.prepare_line(&self.line);
+        let record = if self.too_long {
+            Some(Err(Error::Syntax {
+                line: self.line_number,
+                error: super::Error::LineTooLong,
+            }))
+        } else if self.options.skips(text) {
+            None
+        } else {
+            Some(
+                Record::parse(text, &self.options).map_err(|error| Error::Syntax {
+                    line: self.line_number,
+                    error,
+                }),
+            )
+        };
+
+        self.line.clear();
+        self.too_long = false;
+        record
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for AsyncRecords<R> {
+    type Item = Result<Record, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        if !this.done
+            && this
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+        {
+            this.done = true;
+            return Poll::Ready(Some(Err(Error::Cancelled)));
+        }
+
+        while !this.done {
+            let buf = match Pin::new(&mut this.reader).poll_fill_buf(cx) {
+                Poll::Ready(Ok(buf)) => buf,
+                Poll::Ready(Err(err)) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(Error::Io(err))));
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if buf.is_empty() {
+                this.done = true;
+                if this.line.is_empty() && !this.too_long {
+                    break;
+                }
+            } else {
+                let (len, complete) = match buf.iter().position(|&c| c == b'\n') {
+                    Some(i) => (i + 1, true),
+                    None => (buf.len(), false),
+                };
+
+                // Discard the rest of a line already known to be too long
+                if !this.too_long {
+                    this.line.extend_from_slice(&buf[..len]);
+
+                    if let Some(max) = this.options.max_line_length {
+                        if strip_line_ending(&this.line).len() > max {
+                            this.too_long = true;
+                            this.line.clear();
+                        }
+                    }
+                }
+                Pin::new(&mut this.reader).consume(len);
+
+                if !complete {
+                    continue;
+                }
+            }
+
+            if let Some(record) = this.take_line() {
+                return Poll::Ready(Some(record));
+            }
+        }
+
+        Poll::Ready(None)
+    }
+}
+
+/// Reads records from an asynchronous source of newline separated (either "\n"
+/// or "\r\n") text, returning a stream of them
+///
+/// Lines are read as by [`read_records`](super::read_records), one at a time,
+/// so memory use is bounded by the longest line, and lines longer than
+/// [`DEFAULT_MAX_LINE_LENGTH`](super::DEFAULT_MAX_LINE_LENGTH) are reported as
+/// [`Error::LineTooLong`](super::Error::LineTooLong) without being buffered
+/// whole. Errors carry the line number they occurred on, and a failure of `r`
+/// itself ends the stream after being reported.
+///
+/// Requires the `tokio` feature.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use futures::StreamExt;
+///
+/// let file = tokio::fs::File::open("firmware.mot").await?;
+/// let mut records = srec::reader::read_records_async(tokio::io::BufReader::new(file));
+///
+/// while let Some(record) = records.next().await {
+///     println!("{:?}", record?);
+/// }
+/// ```
+pub fn read_records_async<R: AsyncBufRead + Unpin>(r: R) -> AsyncRecords<R> {
+    read_records_async_with(r, &ReaderOptions::bounded())
+}
+
+/// Reads records from an asynchronous source of newline separated (either "\n"
+/// or "\r\n") text using the provided options, returning a stream of them
+///
+/// [`ReaderOptions::max_line_length`] also limits how much of each line is
+/// buffered. Otherwise behaves exactly as [`read_records_async`].
+///
+/// Requires the `tokio` feature.
+pub fn read_records_async_with<R: AsyncBufRead + Unpin>(
+    r: R,
+    options: &ReaderOptions,
+) -> AsyncRecords<R> {
+    AsyncRecords {
+        reader: r,
+        options: *options,
+        cancel: None,
+        line: Vec::new(),
+        too_long: false,
+        line_number: 0,
+        done: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader;
+    use crate::record::*;
+    use std::task::Waker;
+
+    /// Polls the stream to completion, which never waits as the tests read
+    /// from in-memory buffers
+    fn collect<R: AsyncBufRead + Unpin>(
+        mut records: AsyncRecords<R>,
+    ) -> Vec<Result<Record, Error>> {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut items = Vec::new();
+
+        while let Poll::Ready(Some(item)) = Pin::new(&mut records).poll_next(&mut cx) {
+            items.push(item);
+        }
+
+        items
+    }
+
+    #[test]
+    fn read_records_async_returns_records_and_numbered_errors() {
+        let s = "S00600004844521B\r\n\r\nS107123400010203FF\r\nS9031234B6";
+
+        let items = collect(read_records_async(s.as_bytes()));
+
+        assert_eq!(items.len(), 3);
+        assert!(matches!(items[0], Ok(Record::S0(ref s)) if s == "HDR"));
+        assert!(matches!(
+            items[1],
+            Err(Error::Syntax {
+                line: 3,
+                error: reader::Error::ChecksumMismatch { .. }
+            })
+        ));
+        assert!(matches!(items[2], Ok(Record::S9(Address16(0x1234)))));
+    }
+
+    #[test]
+    fn read_records_async_matches_read_records() {
+        let s = "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n";
+
+        let items = collect(read_records_async(s.as_bytes()))
+            .into_iter()
+            .map(|item| item.ok())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            items,
+            reader::read_records(s).map(Result::ok).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn read_records_async_long_line_returns_err_line_too_long() {
+        let s = format!("S1{}\nS9031234B6\n", "0".repeat(100_000));
+        let options = ReaderOptions {
+            max_line_length: Some(16),
+            ..Default::default()
+        };
+
+        let items = collect(read_records_async_with(s.as_bytes(), &options));
+
+        assert_eq!(items.len(), 2);
+        assert!(matches!(
+            items[0],
+            Err(Error::Syntax {
+                line: 1,
+                error: reader::Error::LineTooLong
+            })
+        ));
+        assert!(matches!(items[1], Ok(Record::S9(Address16(0x1234)))));
+    }
+
+    #[test]
+    fn read_records_async_long_unterminated_last_line_returns_err_line_too_long() {
+        let s = format!("S9031234B6\nS1{}", "0".repeat(100_000));
+        let options = ReaderOptions {
+            max_line_length: Some(16),
+            ..Default::default()
+        };
+
+        let items = collect(read_records_async_with(s.as_bytes(), &options));
+
+        assert_eq!(items.len(), 2);
+        assert!(matches!(items[0], Ok(Record::S9(Address16(0x1234)))));
+        assert!(matches!(
+            items[1],
+            Err(Error::Syntax {
+                line: 2,
+                error: reader::Error::LineTooLong
+            })
+        ));
+    }
+
+    #[test]
+    fn read_records_async_with_cancel_flag_set_returns_err_cancelled_then_ends() {
+        let s = "S00600004844521B\nS9031234B6\n";
+        let cancel = Arc::new(AtomicBool::new(true));
+
+        let items = collect(read_records_async(s.as_bytes()).with_cancel_flag(cancel));
+
+        assert_eq!(items.len(), 1);
+        assert!(matches!(items[0], Err(Error::Cancelled)));
+    }
+}
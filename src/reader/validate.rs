@@ -0,0 +1,424 @@
+use super::{read_records_located, LocatedRecord};
+use crate::analyze::{check_counts, CountError, CountPolicy};
+use crate::record::*;
+use std::error;
+use std::fmt;
+
+/// Errors which may be found when validating a file as a whole
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationError {
+    /// Header record (S0) was not the first record, or was repeated
+    MisplacedHeader {
+        /// Index of the header record
+        index: usize,
+    },
+    /// File had no start address record (S7, S8 or S9) to terminate it
+    MissingTermination,
+    /// Start address record was not the last record, or was repeated
+    MisplacedTermination {
+        /// Index of the start address record
+        index: usize,
+    },
+    /// Data record came after the start address record
+    DataAfterTermination {
+        /// Index of the data record
+        index: usize,
+    },
+    /// Start address record's width did not match the data records, e.g. an
+    /// S9 record terminating S2 data records
+    TerminationWidthMismatch {
+        /// Index of the start address record
+        index: usize,
+    },
+    /// Data record started below the address of the data record before it
+    /// (only reported with [`ValidationOptions::require_ascending`])
+    OutOfOrder {
+        /// Index of the data record
+        index: usize,
+        /// Index of the data record before it
+        previous: usize,
+    },
+    /// Data record overlapped the data record before it (only reported with
+    /// [`ValidationOptions::require_ascending`])
+    Overlap {
+        /// Index of the data record
+        index: usize,
+        /// Index of the data record before it
+        previous: usize,
+    },
+    /// Count record (S5 or S6) was misplaced or did not match the number of
+    /// data records
+    Count(CountError),
+}
+
+impl error::Error for ValidationError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ValidationError::Count(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::MisplacedHeader { index } => {
+                write!(f, "record {}: misplaced header record", index)
+            }
+            ValidationError::MissingTermination => write!(f, "missing start address record"),
+            ValidationError::MisplacedTermination { index } => {
+                write!(f, "record {}: misplaced start address record", index)
+            }
+            ValidationError::DataAfterTermination { index } => {
+                write!(
+                    f,
+                    "record {}: data record after start address record",
+                    index
+                )
+            }
+            ValidationError::TerminationWidthMismatch { index } => write!(
+                f,
+                "record {}: start address record width does not match data records",
+                index
+            ),
+            ValidationError::OutOfOrder { index, previous } => write!(
+                f,
+                "record {}: address below that of data record {}",
+                index, previous
+            ),
+            ValidationError::Overlap { index, previous } => {
+                write!(f, "record {}: overlaps data record {}", index, previous)
+            }
+            ValidationError::Count(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<CountError> for ValidationError {
+    fn from(err: CountError) -> Self {
+        ValidationError::Count(err)
+    }
+}
+
+/// Options controlling how [`validate_with`] checks a file
+///
+/// The default options match the behaviour of [`validate`].
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub struct ValidationOptions {
+    /// Where count records are accepted, and what their count is checked
+    /// against
+    pub count_policy: CountPolicy,
+    /// Require data records to be in ascending address order without
+    /// overlapping, as some flash programmers do
+    pub require_ascending: bool,
+}
+
+/// Returns the data record type matching the width of a start address record
+fn data_type_for(termination: &Record) -> Option<u8> {
+    match termination {
+        Record::S7(_) => Some(3),
+        Record::S8(_) => Some(2),
+        Record::S9(_) => Some(1),
+        _ => None,
+    }
+}
+
+/// Checks the header and start address records are where the format requires
+fn check_structure(records: &[Record]) -> Result<(), ValidationError> {
+    let mut termination = None;
+
+    for (index, record) in records.iter().enumerate() {
+        match record {
+            Record::S0(_) if index != 0 => return Err(ValidationError::MisplacedHeader { index }),
+            Record::S1(_) | Record::S2(_) | Record::S3(_) if termination.is_some() => {
+                return Err(ValidationError::DataAfterTermination { index })
+            }
+            Record::S7(_) | Record::S8(_) | Record::S9(_) => match termination {
+                Some(first) => return Err(ValidationError::MisplacedTermination { index: first }),
+                None => termination = Some(index),
+            },
+            _ => {}
+        }
+    }
+
+    let index = termination.ok_or(ValidationError::MissingTermination)?;
+    if index != records.len() - 1 {
+        return Err(ValidationError::MisplacedTermination { index });
+    }
+
+    let t = data_type_for(&records[index]);
+
+    let mismatched = records.iter().any(|record| match record {
+        Record::S1(_) => t != Some(1),
+        Record::S2(_) => t != Some(2),
+        Record::S3(_) => t != Some(3),
+        _ => false,
+    });
+
+    if mismatched {
+        return Err(ValidationError::TerminationWidthMismatch { index });
+    }
+
+    Ok(())
+}
+
+/// Checks each data record starts at or after the end of the one before it
+fn check_ascending(records: &[Record]) -> Result<(), ValidationError> {
+    let mut previous: Option<(usize, u32, u64)> = None;
+
+    for (index, record) in records.iter().enumerate() {
+        let (address, len) = match record.memory_effect() {
+            MemoryEffect::Write { address, data } => (address, data.len() as u64),
+            _ => continue,
+        };
+
+        if let Some((previous, previous_address, previous_end)) = previous {
+            if address < previous_address {
+                return Err(ValidationError::OutOfOrder { index, previous });
+            }
+            if (address as u64) < previous_end {
+                return Err(ValidationError::Overlap { index, previous });
+            }
+        }
+
+        previous = Some((index, address, address as u64 + len));
+    }
+
+    Ok(())
+}
+
+/// Checks that the records of a file are consistent with each other, returning
+/// the first problem found
+///
+/// Reading records checks each one individually; this checks what can only be
+/// seen across the whole file:
+///
+/// - the header record (S0), if there is one, must be the first record
+/// - exactly one start address record (S7, S8 or S9) must be the last record,
+///   with no data records after it
+/// - the start address record must have the width of the data records (S9
+///   for S1, S8 for S2, S7 for S3)
+/// - a count record (S5 or S6) must come after every data record and give the
+///   number of data records in the file, although a file need not have one
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::analyze::CountError;
+/// use srec::reader::ValidationError;
+///
+/// let records = srec::read_records("S107123400010203AC\nS5030002FA\nS9031234B6\n")
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+///
+/// assert_eq!(
+///     srec::reader::validate(&records),
+///     Err(ValidationError::Count(CountError::Mismatch {
+///         index: 1,
+///         expected: 1,
+///         found: 2,
+///     }))
+/// );
+/// ```
+pub fn validate(records: &[Record]) -> Result<(), ValidationError> {
+    validate_with(records, &ValidationOptions::default())
+}
+
+/// Checks that the records of a file are consistent with each other using the
+/// provided options, returning the first problem found
+///
+/// Otherwise behaves exactly as [`validate`].
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::reader::{ValidationError, ValidationOptions};
+///
+/// let records = srec::read_records("S10712380405060798\nS107123400010203AC\nS9031234B6\n")
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// let options = ValidationOptions {
+///     require_ascending: true,
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(srec::reader::validate(&records), Ok(()));
+/// assert_eq!(
+///     srec::reader::validate_with(&records, &options),
+///     Err(ValidationError::OutOfOrder {
+///         index: 1,
+///         previous: 0
+///     })
+/// );
+/// ```
+pub fn validate_with(
+    records: &[Record],
+    options: &ValidationOptions,
+) -> Result<(), ValidationError> {
+    check_structure(records)?;
+    if options.require_ascending {
+        check_ascending(records)?;
+    }
+    check_counts(records, options.count_policy)?;
+
+    Ok(())
+}
+
+/// Reads records from a newline separated (either "\n" or "\r\n") string and
+/// checks them as [`validate_with`] does, returning the records if both
+/// succeed
+///
+/// The first line which cannot be read is returned as
+/// [`crate::Error::Syntax`] with its line number, and the first problem found
+/// across the file as [`crate::Error::Semantic`].
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::reader::ValidationError;
+///
+/// let r = srec::reader::read_records_validated(
+///     "S107123400010203AC\nS9031234B6\nS9031234B6\n",
+///     &Default::default(),
+/// );
+///
+/// assert!(matches!(
+///     r,
+///     Err(srec::Error::Semantic(ValidationError::MisplacedTermination {
+///         index: 1
+///     }))
+/// ));
+/// ```
+pub fn read_records_validated(
+    s: &str,
+    options: &ValidationOptions,
+) -> Result<Vec<Record>, crate::Error> {
+    let records = read_records_located(s)
+        .map(|LocatedRecord { line, record, .. }| {
+            record.map_err(|error| crate::Error::Syntax { line, error })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    validate_with(&records, options)?;
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_matching_count_returns_ok() {
+        let records = [
+            Record::S1(Data {
+                address: Address16(0x1234),
+                data: vec![0x00],
+            }),
+            Record::S5(Count16(1)),
+            Record::S9(Address16(0x1234)),
+        ];
+
+        let r = validate(&records);
+
+        assert_eq!(r, Ok(()));
+    }
+
+    #[test]
+    fn validate_with_checkpoint_policy_accepts_leading_zero_count() {
+        let records = [
+            Record::S5(Count16(0)),
+            Record::S1(Data {
+                address: Address16(0x1234),
+                data: vec![0x00],
+            }),
+            Record::S9(Address16(0x1234)),
+        ];
+        let options = ValidationOptions {
+            count_policy: CountPolicy::Checkpoint,
+            ..Default::default()
+        };
+
+        let r = validate(&records);
+        let r2 = validate_with(&records, &options);
+
+        assert_eq!(
+            r,
+            Err(ValidationError::Count(CountError::Misplaced { index: 0 }))
+        );
+        assert_eq!(r2, Ok(()));
+    }
+
+    fn s1(address: u16) -> Record {
+        Record::S1(Data {
+            address: Address16(address),
+            data: vec![0x00],
+        })
+    }
+
+    #[test]
+    fn validate_structure_problems_return_err() {
+        let cases = [
+            (
+                vec![s1(0), Record::S0("HDR".into()), Record::S9(Address16(0))],
+                ValidationError::MisplacedHeader { index: 1 },
+            ),
+            (vec![s1(0)], ValidationError::MissingTermination),
+            (
+                vec![Record::S9(Address16(0)), Record::S5(Count16(0))],
+                ValidationError::MisplacedTermination { index: 0 },
+            ),
+            (
+                vec![Record::S9(Address16(0)), s1(0)],
+                ValidationError::DataAfterTermination { index: 1 },
+            ),
+            (
+                vec![s1(0), Record::S8(Address24(0))],
+                ValidationError::TerminationWidthMismatch { index: 1 },
+            ),
+        ];
+
+        for (records, error) in &cases {
+            let r = validate(records);
+
+            assert_eq!(r, Err(*error), "{:?}", records);
+        }
+    }
+
+    #[test]
+    fn validate_with_require_ascending_overlap_returns_err_overlap() {
+        let records = [
+            Record::S1(Data {
+                address: Address16(0x1234),
+                data: vec![0x00, 0x01],
+            }),
+            s1(0x1235),
+            Record::S9(Address16(0x1234)),
+        ];
+        let options = ValidationOptions {
+            require_ascending: true,
+            ..Default::default()
+        };
+
+        let r = validate_with(&records, &options);
+
+        assert_eq!(
+            r,
+            Err(ValidationError::Overlap {
+                index: 1,
+                previous: 0
+            })
+        );
+    }
+
+    #[test]
+    fn read_records_validated_syntax_error_returns_err_syntax() {
+        let s = "S107123400010203AC\nS9031234FF\n";
+
+        let r = read_records_validated(s, &ValidationOptions::default());
+
+        assert!(matches!(r, Err(crate::Error::Syntax { line: 2, .. })));
+    }
+}
@@ -1,3 +1,8 @@
+use std::error;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::slice;
+
 /// Allows conversion of an address into a vector of big-endian bytes
 pub trait Address {
     /// Returns the bytes of the address value in big-endian
@@ -5,7 +10,7 @@ pub trait Address {
 }
 
 /// 16-bit address
-#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Address16(pub u16);
 
 impl Address for Address16 {
@@ -22,7 +27,7 @@ impl From<Address16> for u32 {
 
 // TODO: Restrict the value to 24 bits
 /// 24-bit address
-#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Address24(pub u32);
 
 impl Address for Address24 {
@@ -38,7 +43,7 @@ impl From<Address24> for u32 {
 }
 
 /// 32-bit address
-#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Address32(pub u32);
 
 impl Address for Address32 {
@@ -54,7 +59,7 @@ impl From<Address32> for u32 {
 }
 
 /// 16-bit data record count
-#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Count16(pub u16);
 
 impl From<Count16> for u32 {
@@ -65,7 +70,7 @@ impl From<Count16> for u32 {
 
 // TODO: Restrict the value to 24 bits
 /// 24-bit data record count
-#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Count24(pub u32);
 
 impl From<Count24> for u32 {
@@ -75,7 +80,7 @@ impl From<Count24> for u32 {
 }
 
 /// Record data field
-#[derive(Debug, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug, Clone, Default, Hash, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Data<T> {
     /// Start address
     pub address: T,
@@ -83,6 +88,238 @@ pub struct Data<T> {
     pub data: Vec<u8>,
 }
 
+impl<T> Data<T> {
+    /// Creates a data field at `address` with no data bytes
+    pub fn new(address: T) -> Self {
+        Data {
+            address,
+            data: Vec::new(),
+        }
+    }
+
+    /// Sets the start address, returning the field so calls can be chained
+    pub fn with_address(mut self, address: T) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// Sets the data bytes, returning the field so calls can be chained
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use srec::{Address16, Data};
+    ///
+    /// let d = Data::default()
+    ///     .with_address(Address16(0x1234))
+    ///     .with_data([0x00, 0x01]);
+    ///
+    /// assert_eq!(d, Data::new(Address16(0x1234)).with_data(vec![0x00, 0x01]));
+    /// ```
+    pub fn with_data(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.data = data.into();
+        self
+    }
+}
+
+/// Gives direct access to the data bytes, so e.g. `data.len()`, `data[0]` and
+/// `data.iter()` can be used in place of going through the `data` field
+///
+/// # Examples
+///
+/// ```rust
+/// let d = srec::Data {
+///     address: srec::Address16(0x1234),
+///     data: vec![0x00, 0x01, 0x02],
+/// };
+///
+/// assert_eq!(d.len(), 3);
+/// assert_eq!(d[1], 0x01);
+/// assert_eq!(d.iter().sum::<u8>(), 0x03);
+/// ```
+impl<T> Deref for Data<T> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl<T> DerefMut for Data<T> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Data<T> {
+    type Item = &'a u8;
+    type IntoIter = slice::Iter<'a, u8>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter()
+    }
+}
+
+/// Maximum length in bytes of the string in an S0 header record: the 255 bytes
+/// described by the byte count, less the 2 byte address and 1 byte checksum
+pub const MAX_HEADER_LEN: usize = 252;
+
+/// Errors which may occur when creating a [`HeaderString`]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+pub enum HeaderError {
+    /// String was longer than [`MAX_HEADER_LEN`] bytes (or the requested
+    /// padded length)
+    TooLong,
+    /// Bytes were not valid UTF-8
+    InvalidUtf8,
+}
+
+impl error::Error for HeaderError {}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                HeaderError::TooLong => "header too long",
+                HeaderError::InvalidUtf8 => "header not valid UTF-8",
+            }
+        )
+    }
+}
+
+/// Header string which is guaranteed to fit in an S0 record
+///
+/// `Record::S0` holds a plain `String`, which produces a corrupt record when
+/// longer than [`MAX_HEADER_LEN`] bytes. Building headers through this type
+/// reports oversize headers when they are created instead.
+///
+/// # Examples
+///
+/// ```rust
+/// let header = srec::HeaderString::new("HDR").unwrap();
+///
+/// assert_eq!(srec::Record::from(header), srec::Record::S0("HDR".into()));
+/// assert_eq!(
+///     srec::HeaderString::new("x".repeat(253)),
+///     Err(srec::HeaderError::TooLong)
+/// );
+/// ```
+#[derive(Debug, Clone, Default, Hash, PartialEq, PartialOrd, Eq, Ord)]
+pub struct HeaderString(String);
+
+impl HeaderString {
+    /// Creates a header from a string, failing if it is longer than
+    /// [`MAX_HEADER_LEN`] bytes
+    pub fn new(s: impl Into<String>) -> Result<Self, HeaderError> {
+        let s = s.into();
+
+        if s.len() > MAX_HEADER_LEN {
+            return Err(HeaderError::TooLong);
+        }
+
+        Ok(HeaderString(s))
+    }
+
+    /// Creates a header from UTF-8 bytes, failing if they are not valid UTF-8
+    /// or longer than [`MAX_HEADER_LEN`] bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HeaderError> {
+        let s = String::from_utf8(bytes.to_vec()).map_err(|_| HeaderError::InvalidUtf8)?;
+
+        HeaderString::new(s)
+    }
+
+    /// Creates a header padded with NUL characters to exactly `len` bytes, as
+    /// used by toolchains which emit fixed-width headers
+    ///
+    /// Fails if the string is longer than `len`, or `len` is longer than
+    /// [`MAX_HEADER_LEN`]. The reader strips trailing NULs, so a padded header
+    /// reads back as the original string.
+    pub fn padded(s: &str, len: usize) -> Result<Self, HeaderError> {
+        if s.len() > len {
+            return Err(HeaderError::TooLong);
+        }
+
+        let mut s = s.to_string();
+        s.extend(std::iter::repeat_n('\0', len - s.len()));
+
+        HeaderString::new(s)
+    }
+
+    /// Returns the header as a string slice
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Converts the header into a `String`
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl AsRef<str> for HeaderString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for HeaderString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<HeaderString> for Record {
+    fn from(header: HeaderString) -> Record {
+        Record::S0(header.0)
+    }
+}
+
+/// The framing of a single record, with its payload uninterpreted
+///
+/// Read with [`raw_record_from_str`](crate::reader::raw_record_from_str) and
+/// written with `RawRecord::encode` (with the `writer` feature).
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct RawRecord {
+    /// Record type, the digit following the "S"
+    pub t: u8,
+    /// Payload bytes - the address and data - excluding the byte count and
+    /// checksum
+    pub bytes: Vec<u8>,
+}
+
+impl RawRecord {
+    /// Returns the header data of an S0 record as a string, replacing
+    /// sequences which are not valid UTF-8 with U+FFFD and removing trailing
+    /// NUL padding
+    ///
+    /// Returns `None` if the record is not an S0 record or is too short to
+    /// hold an address. Unlike reading the record as a [`Record`], never fails
+    /// for binary headers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let rr = srec::RawRecord {
+    ///     t: 0,
+    ///     bytes: vec![0x00, 0x00, 0xff, 0x48, 0x44, 0x00],
+    /// };
+    ///
+    /// assert_eq!(rr.header_lossy().as_deref(), Some("\u{fffd}HD"));
+    /// ```
+    pub fn header_lossy(&self) -> Option<String> {
+        match (self.t, self.bytes.get(2..)) {
+            (0, Some(data)) => Some(
+                String::from_utf8_lossy(data)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            ),
+            _ => None,
+        }
+    }
+}
+
 /// An SRecord
 ///
 /// See [Wikipedia](https://en.wikipedia.org/wiki/SREC_(file_format)#Record_types)
@@ -108,6 +345,193 @@ pub enum Record {
     S8(Address24),
     /// 16-bit start address
     S9(Address16),
+    /// Record of a type with no standard meaning (e.g. S4), kept as read so it
+    /// can be inspected or passed through
+    ///
+    /// Only produced when reading with
+    /// [`UnknownRecords::Keep`](crate::reader::UnknownRecords::Keep). `t`
+    /// should be a single digit, and `bytes` holds everything between the
+    /// byte count and checksum.
+    Unknown {
+        /// Record type
+        t: u8,
+        /// Payload bytes, excluding the byte count and checksum
+        bytes: Vec<u8>,
+    },
+}
+
+/// What loading a record does, with the width of its address or count field
+/// discarded
+///
+/// Two records from files produced by different toolchains have the same
+/// effect if they differ only in width, e.g. `S1` and `S3` records writing the
+/// same bytes to the same address. The value can be hashed, so sets of effects
+/// can be compared between files.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+pub enum MemoryEffect<'a> {
+    /// Header (S0) string
+    Header(&'a str),
+    /// Data (S1, S2 or S3) written at an address
+    Write {
+        /// Start address
+        address: u32,
+        /// Data bytes
+        data: &'a [u8],
+    },
+    /// Data record count (S5 or S6)
+    Count(u32),
+    /// Start address (S7, S8 or S9)
+    StartAddress(u32),
+    /// Record of a type with no standard meaning, so no known effect
+    Unknown {
+        /// Record type
+        t: u8,
+        /// Payload bytes
+        bytes: &'a [u8],
+    },
+}
+
+impl Record {
+    /// Returns the effect of loading the record, independent of its width
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let r = srec::Record::S1(srec::Data {
+    ///     address: srec::Address16(0x1234),
+    ///     data: vec![0xaa],
+    /// });
+    ///
+    /// assert_eq!(
+    ///     r.memory_effect(),
+    ///     srec::MemoryEffect::Write {
+    ///         address: 0x1234,
+    ///         data: &[0xaa],
+    ///     }
+    /// );
+    /// ```
+    pub fn memory_effect(&self) -> MemoryEffect<'_> {
+        match self {
+            Record::S0(s) => MemoryEffect::Header(s),
+            Record::S1(Data { address, data }) => MemoryEffect::Write {
+                address: (*address).into(),
+                data,
+            },
+            Record::S2(Data { address, data }) => MemoryEffect::Write {
+                address: (*address).into(),
+                data,
+            },
+            Record::S3(Data { address, data }) => MemoryEffect::Write {
+                address: (*address).into(),
+                data,
+            },
+            Record::S5(count) => MemoryEffect::Count((*count).into()),
+            Record::S6(count) => MemoryEffect::Count((*count).into()),
+            Record::S7(address) => MemoryEffect::StartAddress((*address).into()),
+            Record::S8(address) => MemoryEffect::StartAddress((*address).into()),
+            Record::S9(address) => MemoryEffect::StartAddress((*address).into()),
+            Record::Unknown { t, bytes } => MemoryEffect::Unknown { t: *t, bytes },
+        }
+    }
+
+    /// Returns true if loading either record has the same effect, ignoring
+    /// differences in address or count width
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let r1 = srec::Record::S1(srec::Data {
+    ///     address: srec::Address16(0x0010),
+    ///     data: vec![0xaa],
+    /// });
+    /// let r2 = srec::Record::S3(srec::Data {
+    ///     address: srec::Address32(0x00000010),
+    ///     data: vec![0xaa],
+    /// });
+    ///
+    /// assert_ne!(r1, r2);
+    /// assert!(r1.same_memory_effect(&r2));
+    /// ```
+    pub fn same_memory_effect(&self, other: &Record) -> bool {
+        self.memory_effect() == other.memory_effect()
+    }
+}
+
+/// One linked module of a file holding several: a header, data, count and
+/// start address sequence
+///
+/// Older linkers concatenate one such sequence per module into a single file.
+/// Read with [`read_modules`](crate::reader::read_modules) and written with
+/// `writer::generate_modules` (with the `writer` feature).
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct Module {
+    /// Header (S0) string, if the module has one
+    pub header: Option<String>,
+    /// Data records, along with any records of unknown type, in file order
+    pub data: Vec<Record>,
+    /// Data record count given by the module's count record (S5 or S6), if
+    /// it has one
+    pub count: Option<u32>,
+    /// Start address given by the module's start address record (S7, S8 or
+    /// S9), if it has one
+    pub start: Option<u32>,
+}
+
+impl Module {
+    /// Returns true if the module holds no records
+    pub fn is_empty(&self) -> bool {
+        self.header.is_none()
+            && self.data.is_empty()
+            && self.count.is_none()
+            && self.start.is_none()
+    }
+
+    /// Returns the records of the module in file order
+    ///
+    /// The count is written as an S5 record if it fits in 16 bits, otherwise
+    /// as S6. A count which does not fit in the 24 bits of an S6 record is
+    /// still returned as one, and fails to encode with
+    /// `writer::Error::CountTooLarge`. The start address record has the width
+    /// of the widest data record, widened further if the address does not
+    /// fit.
+    pub fn to_records(&self) -> Vec<Record> {
+        let mut records = Vec::with_capacity(self.data.len() + 3);
+
+        if let Some(header) = &self.header {
+            records.push(Record::S0(header.clone()));
+        }
+
+        records.extend(self.data.iter().cloned());
+
+        if let Some(count) = self.count {
+            records.push(if count <= 0xffff {
+                Record::S5(Count16(count as u16))
+            } else {
+                Record::S6(Count24(count))
+            });
+        }
+
+        if let Some(start) = self.start {
+            let width = self
+                .data
+                .iter()
+                .map(|r| match r {
+                    Record::S2(_) => 3,
+                    Record::S3(_) => 4,
+                    _ => 2,
+                })
+                .max()
+                .unwrap_or(2);
+
+            records.push(match start {
+                0..=0xffff if width == 2 => Record::S9(Address16(start as u16)),
+                0..=0xff_ffff if width <= 3 => Record::S8(Address24(start)),
+                _ => Record::S7(Address32(start)),
+            });
+        }
+
+        records
+    }
 }
 
 #[cfg(test)]
@@ -185,4 +609,202 @@ mod tests {
 
         assert_eq!(b, 0x123456);
     }
+
+    #[test]
+    fn header_string_new_maximum_length_returns_ok() {
+        let s = "x".repeat(MAX_HEADER_LEN);
+
+        let h = HeaderString::new(s.clone());
+
+        assert_eq!(h.map(HeaderString::into_string), Ok(s));
+    }
+
+    #[test]
+    fn header_string_new_too_long_returns_err_too_long() {
+        let s = "x".repeat(MAX_HEADER_LEN + 1);
+
+        let h = HeaderString::new(s);
+
+        assert_eq!(h, Err(HeaderError::TooLong));
+    }
+
+    #[test]
+    fn header_string_from_bytes_valid_returns_ok() {
+        let h = HeaderString::from_bytes(b"HDR");
+
+        assert_eq!(h.as_ref().map(HeaderString::as_str), Ok("HDR"));
+    }
+
+    #[test]
+    fn header_string_from_bytes_invalid_utf8_returns_err_invalid_utf8() {
+        let h = HeaderString::from_bytes(&[0x48, 0xff]);
+
+        assert_eq!(h, Err(HeaderError::InvalidUtf8));
+    }
+
+    #[test]
+    fn header_string_padded_pads_with_nul() {
+        let h = HeaderString::padded("HDR", 6);
+
+        assert_eq!(h.as_ref().map(HeaderString::as_str), Ok("HDR\0\0\0"));
+    }
+
+    #[test]
+    fn header_string_padded_string_longer_than_len_returns_err_too_long() {
+        let h = HeaderString::padded("HDR", 2);
+
+        assert_eq!(h, Err(HeaderError::TooLong));
+    }
+
+    #[test]
+    fn header_string_padded_len_too_long_returns_err_too_long() {
+        let h = HeaderString::padded("HDR", MAX_HEADER_LEN + 1);
+
+        assert_eq!(h, Err(HeaderError::TooLong));
+    }
+
+    #[test]
+    fn header_string_into_record_returns_s0() {
+        let h = HeaderString::new("HDR").unwrap();
+
+        let r = Record::from(h);
+
+        assert_eq!(r, Record::S0("HDR".into()));
+    }
+
+    #[test]
+    fn same_memory_effect_data_records_of_different_widths_returns_true() {
+        let r1 = Record::S1(Data {
+            address: Address16(0x0010),
+            data: vec![0xaa],
+        });
+        let r2 = Record::S2(Data {
+            address: Address24(0x000010),
+            data: vec![0xaa],
+        });
+        let r3 = Record::S3(Data {
+            address: Address32(0x00000010),
+            data: vec![0xaa],
+        });
+
+        assert!(r1.same_memory_effect(&r2));
+        assert!(r2.same_memory_effect(&r3));
+        assert!(r3.same_memory_effect(&r1));
+    }
+
+    #[test]
+    fn same_memory_effect_different_data_returns_false() {
+        let r1 = Record::S1(Data {
+            address: Address16(0x0010),
+            data: vec![0xaa],
+        });
+        let r2 = Record::S3(Data {
+            address: Address32(0x00000010),
+            data: vec![0xbb],
+        });
+
+        assert!(!r1.same_memory_effect(&r2));
+    }
+
+    #[test]
+    fn same_memory_effect_different_address_returns_false() {
+        let r1 = Record::S1(Data {
+            address: Address16(0x0010),
+            data: vec![0xaa],
+        });
+        let r2 = Record::S3(Data {
+            address: Address32(0x00010010),
+            data: vec![0xaa],
+        });
+
+        assert!(!r1.same_memory_effect(&r2));
+    }
+
+    #[test]
+    fn same_memory_effect_counts_and_start_addresses_of_different_widths_returns_true() {
+        assert!(Record::S5(Count16(0x0010)).same_memory_effect(&Record::S6(Count24(0x000010))));
+        assert!(Record::S9(Address16(0x0010)).same_memory_effect(&Record::S7(Address32(0x10))));
+        assert!(Record::S8(Address24(0x0010)).same_memory_effect(&Record::S9(Address16(0x10))));
+    }
+
+    #[test]
+    fn same_memory_effect_count_and_start_address_with_same_value_returns_false() {
+        assert!(!Record::S5(Count16(0x0010)).same_memory_effect(&Record::S9(Address16(0x0010))));
+    }
+
+    #[test]
+    fn memory_effect_can_be_collected_into_set() {
+        let r = [
+            Record::S1(Data {
+                address: Address16(0x0010),
+                data: vec![0xaa],
+            }),
+            Record::S3(Data {
+                address: Address32(0x00000010),
+                data: vec![0xaa],
+            }),
+        ];
+
+        let effects = r
+            .iter()
+            .map(Record::memory_effect)
+            .collect::<std::collections::HashSet<_>>();
+
+        assert_eq!(effects.len(), 1);
+    }
+
+    #[test]
+    fn data_deref_gives_access_to_bytes() {
+        let mut d = Data {
+            address: Address16(0x1234),
+            data: vec![0x00, 0x01, 0x02],
+        };
+
+        d[0] = 0xff;
+
+        assert_eq!(d.len(), 3);
+        assert!(!d.is_empty());
+        assert_eq!(&*d, [0xff, 0x01, 0x02]);
+        assert_eq!(
+            (&d).into_iter().copied().collect::<Vec<_>>(),
+            [0xff, 0x01, 0x02]
+        );
+    }
+
+    #[test]
+    fn module_to_records_start_address_matches_widest_data_record() {
+        let module = Module {
+            data: vec![Record::S2(Data {
+                address: Address24(0x10000),
+                data: vec![0xaa],
+            })],
+            count: Some(0x10000),
+            start: Some(0x1234),
+            ..Default::default()
+        };
+
+        let records = module.to_records();
+
+        assert_eq!(records[1], Record::S6(Count24(0x10000)));
+        assert_eq!(records[2], Record::S8(Address24(0x1234)));
+    }
+
+    #[test]
+    fn module_to_records_count_above_24_bits_returns_s6_record() {
+        let module = Module {
+            count: Some(0x100_0000),
+            start: Some(0x1234),
+            ..Default::default()
+        };
+
+        let records = module.to_records();
+
+        assert_eq!(
+            records,
+            [
+                Record::S6(Count24(0x100_0000)),
+                Record::S9(Address16(0x1234))
+            ]
+        );
+    }
 }
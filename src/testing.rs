@@ -0,0 +1,395 @@
+//! Comparison of records for use in tests
+//!
+//! Comparing long record lists with `assert_eq!` prints both lists in full on
+//! failure, which for a real firmware image is thousands of lines. The
+//! functions here instead report only the records which differ, one line
+//! each, with the first differing byte of mismatched data records.
+//!
+//! Requires the `testing` feature.
+#[cfg(feature = "reader")]
+use crate::reader;
+#[cfg(all(feature = "reader", feature = "writer"))]
+use crate::reader::{ReaderOptions, UnknownRecords};
+use crate::record::*;
+use std::fmt::Write;
+
+/// Maximum number of mismatched records described in a report before the
+/// rest are summarised
+const MAX_REPORTED: usize = 10;
+
+/// Returns a one line summary of a record which does not include its data
+fn describe(record: &Record) -> String {
+    match record {
+        Record::S0(s) => format!("S0 {:?}", s),
+        Record::S1(Data { address, data }) => {
+            format!("S1 at {:#06X}, {} bytes", address.0, data.len())
+        }
+        Record::S2(Data { address, data }) => {
+            format!("S2 at {:#08X}, {} bytes", address.0, data.len())
+        }
+        Record::S3(Data { address, data }) => {
+            format!("S3 at {:#010X}, {} bytes", address.0, data.len())
+        }
+        Record::S5(count) => format!("S5 count {}", count.0),
+        Record::S6(count) => format!("S6 count {}", count.0),
+        Record::S7(address) => format!("S7 start {:#010X}", address.0),
+        Record::S8(address) => format!("S8 start {:#08X}", address.0),
+        Record::S9(address) => format!("S9 start {:#06X}", address.0),
+        Record::Unknown { t, bytes } => format!("S{} (unknown), {} bytes", t, bytes.len()),
+    }
+}
+
+/// Describes where two data records writing to the same address first differ
+fn data_difference(expected: &Record, actual: &Record) -> Option<String> {
+    let (address, expected, actual) = match (expected.memory_effect(), actual.memory_effect()) {
+        (
+            MemoryEffect::Write {
+                address,
+                data: expected,
+            },
+            MemoryEffect::Write {
+                address: actual_address,
+                data: actual,
+            },
+        ) if address == actual_address => (address, expected, actual),
+        _ => return None,
+    };
+
+    let i = expected.iter().zip(actual).position(|(e, a)| e != a)?;
+
+    Some(format!(
+        "first difference at {:#010X}: expected {:#04X}, found {:#04X}",
+        address as u64 + i as u64,
+        expected[i],
+        actual[i]
+    ))
+}
+
+/// Compares two lists item by item, returning a report of the items which
+/// differ
+fn diff<T: PartialEq>(
+    expected: &[T],
+    actual: &[T],
+    describe: impl Fn(&T) -> String,
+    detail: impl Fn(&T, &T) -> Option<String>,
+) -> Option<String> {
+    let mut report = String::new();
+    let mut mismatches = 0;
+
+    for i in 0..expected.len().max(actual.len()) {
+        let line = match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => continue,
+            (Some(e), Some(a)) => {
+                let mut line = format!("expected {}, found {}", describe(e), describe(a));
+                if let Some(detail) = detail(e, a) {
+                    line.push_str("; ");
+                    line.push_str(&detail);
+                }
+                line
+            }
+            (Some(e), None) => format!("missing {}", describe(e)),
+            (None, Some(a)) => format!("unexpected {}", describe(a)),
+            (None, None) => continue,
+        };
+
+        mismatches += 1;
+        if mismatches <= MAX_REPORTED {
+            let _ = writeln!(report, "  record {}: {}", i + 1, line);
+        }
+    }
+
+    if mismatches == 0 {
+        return None;
+    }
+
+    if mismatches > MAX_REPORTED {
+        let _ = writeln!(report, "  ... and {} more", mismatches - MAX_REPORTED);
+    }
+
+    Some(format!(
+        "{} of {} records differ ({} expected, {} actual):\n{}",
+        mismatches,
+        expected.len().max(actual.len()),
+        expected.len(),
+        actual.len(),
+        report
+    ))
+}
+
+/// Compares two lists of records, returning a report of the records which
+/// differ or `None` if the lists are equal
+///
+/// Records are compared by position. Each mismatched record is described on
+/// one line without its data; for data records writing to the same address,
+/// the first differing byte is given instead. At most 10 mismatches are
+/// described.
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::{Address16, Data, Record};
+///
+/// let expected = [Record::S1(Data {
+///     address: Address16(0x1234),
+///     data: vec![0x00, 0x01, 0x02, 0x03],
+/// })];
+/// let actual = [Record::S1(Data {
+///     address: Address16(0x1234),
+///     data: vec![0x00, 0x01, 0xff, 0x03],
+/// })];
+///
+/// let report = srec::testing::diff_records(&expected, &actual).unwrap();
+///
+/// assert!(report.contains("first difference at 0x00001236: expected 0x02, found 0xFF"));
+/// ```
+pub fn diff_records(expected: &[Record], actual: &[Record]) -> Option<String> {
+    diff(expected, actual, describe, data_difference)
+}
+
+/// Reads two SREC files and compares their records, returning a report of the
+/// records which differ or `None` if the files contain the same records
+///
+/// Lines which cannot be read as records are compared by their error. Blank
+/// lines, line endings and the case of hex digits are not significant.
+/// Otherwise behaves as [`diff_records`].
+///
+/// Also requires the `reader` feature.
+#[cfg(feature = "reader")]
+pub fn diff_srec_files(expected: &str, actual: &str) -> Option<String> {
+    let expected = reader::read_records(expected).collect::<Vec<_>>();
+    let actual = reader::read_records(actual).collect::<Vec<_>>();
+
+    diff(
+        &expected,
+        &actual,
+        |r| match r {
+            Ok(record) => describe(record),
+            Err(err) => format!("error ({})", err),
+        },
+        |e, a| match (e, a) {
+            (Ok(e), Ok(a)) => data_difference(e, a),
+            _ => None,
+        },
+    )
+}
+
+/// Encodes a record, reads it back and encodes it again, returning a
+/// description of the first step which did not reproduce the record
+///
+/// Records of unknown type are read back as [`Record::Unknown`]. Useful in
+/// property tests of code which builds records, to check that what it builds
+/// survives being written to a file, e.g. that headers are not too long and
+/// do not end in NUL padding which reading strips.
+///
+/// Also requires the `reader` and `writer` features.
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::testing::roundtrip_check;
+/// use srec::{Address32, Record};
+///
+/// assert_eq!(roundtrip_check(&Record::S7(Address32(0xffff_ffff))), Ok(()));
+/// assert!(roundtrip_check(&Record::S0("HDR\0".into())).is_err());
+/// ```
+#[cfg(all(feature = "reader", feature = "writer"))]
+pub fn roundtrip_check(record: &Record) -> Result<(), String> {
+    let options = ReaderOptions {
+        unknown_records: UnknownRecords::Keep,
+        ..Default::default()
+    };
+
+    let mut encoded = String::new();
+    record
+        .encode_to(&mut encoded)
+        .map_err(|err| format!("{:?} could not be encoded ({})", record, err))?;
+
+    let read = Record::parse(encoded.as_bytes(), &options).map_err(|err| {
+        format!(
+            "{:?} encoded as {} which reads as error ({})",
+            record, encoded, err
+        )
+    })?;
+    if read != *record {
+        return Err(format!(
+            "{:?} encoded as {} which reads as {:?}",
+            record, encoded, read
+        ));
+    }
+
+    let mut reencoded = String::new();
+    read.encode_to(&mut reencoded).map_err(|err| {
+        format!(
+            "{:?} read back as {:?} which could not be encoded ({})",
+            record, read, err
+        )
+    })?;
+    if reencoded != encoded {
+        return Err(format!(
+            "{:?} encoded as {} but re-encoded as {}",
+            record, encoded, reencoded
+        ));
+    }
+
+    Ok(())
+}
+
+/// Asserts that two lists of records are equal, panicking with a report of
+/// the records which differ if not
+///
+/// Both arguments may be anything which dereferences to `[Record]`, e.g.
+/// `Vec<Record>` or an array of records. See
+/// [`diff_records`](crate::testing::diff_records) for the report format.
+///
+/// Requires the `testing` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::{Address16, Record};
+///
+/// let records = vec![Record::S0("HDR".into()), Record::S9(Address16(0x1234))];
+///
+/// srec::assert_srec_eq!(records, [Record::S0("HDR".into()), Record::S9(Address16(0x1234))]);
+/// ```
+#[macro_export]
+macro_rules! assert_srec_eq {
+    ($expected:expr, $actual:expr $(,)?) => {
+        if let Some(report) = $crate::testing::diff_records(&$expected, &$actual) {
+            panic!("assertion failed: records differ\n{}", report);
+        }
+    };
+}
+
+/// Asserts that two SREC files contain the same records, panicking with a
+/// report of the records which differ if not
+///
+/// See [`diff_srec_files`](crate::testing::diff_srec_files) for how the
+/// files are compared.
+///
+/// Requires the `testing` and `reader` features.
+///
+/// # Examples
+///
+/// ```rust
+/// srec::assert_srec_files_eq!(
+///     "S00600004844521B\nS9031234B6\n",
+///     "S00600004844521b\r\n\r\nS9031234b6\r\n",
+/// );
+/// ```
+#[cfg(feature = "reader")]
+#[macro_export]
+macro_rules! assert_srec_files_eq {
+    ($expected:expr, $actual:expr $(,)?) => {
+        if let Some(report) = $crate::testing::diff_srec_files(&$expected, &$actual) {
+            panic!("assertion failed: SREC files differ\n{}", report);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s1(address: u16, data: &[u8]) -> Record {
+        Record::S1(Data {
+            address: Address16(address),
+            data: data.to_vec(),
+        })
+    }
+
+    #[test]
+    fn diff_records_equal_lists_returns_none() {
+        let records = [s1(0x1234, &[0x00, 0x01]), Record::S9(Address16(0x1234))];
+
+        let report = diff_records(&records, &records);
+
+        assert_eq!(report, None);
+    }
+
+    #[test]
+    fn diff_records_mismatched_data_reports_first_differing_byte() {
+        let expected = [s1(0x1234, &[0x00, 0x01, 0x02])];
+        let actual = [s1(0x1234, &[0x00, 0xff, 0xff])];
+
+        let report = diff_records(&expected, &actual);
+
+        assert_eq!(
+            report.as_deref(),
+            Some(
+                "1 of 1 records differ (1 expected, 1 actual):\n  \
+                 record 1: expected S1 at 0x1234, 3 bytes, found S1 at 0x1234, 3 bytes; \
+                 first difference at 0x00001235: expected 0x01, found 0xFF\n"
+            )
+        );
+    }
+
+    #[test]
+    fn diff_records_different_lengths_reports_missing_and_unexpected() {
+        let expected = [s1(0x1234, &[0x00]), Record::S9(Address16(0x1234))];
+        let actual = [s1(0x1234, &[0x00])];
+
+        let report = diff_records(&expected, &actual);
+        let report2 = diff_records(&actual, &expected);
+
+        assert!(report
+            .unwrap()
+            .contains("record 2: missing S9 start 0x1234"));
+        assert!(report2
+            .unwrap()
+            .contains("record 2: unexpected S9 start 0x1234"));
+    }
+
+    #[test]
+    fn diff_records_many_mismatches_summarises_remainder() {
+        let expected = (0..15).map(|i| s1(i, &[0x00])).collect::<Vec<_>>();
+        let actual = (0..15).map(|i| s1(i, &[0x01])).collect::<Vec<_>>();
+
+        let report = diff_records(&expected, &actual).unwrap();
+
+        assert_eq!(report.lines().count(), 1 + MAX_REPORTED + 1);
+        assert!(report.ends_with("  ... and 5 more\n"));
+    }
+
+    #[cfg(feature = "reader")]
+    #[test]
+    fn diff_srec_files_reports_read_errors() {
+        let expected = "S00600004844521B\nS9031234B6\n";
+        let actual = "S00600004844521B\nS9031234B7\n";
+
+        let report = diff_srec_files(expected, actual);
+
+        assert!(report
+            .unwrap()
+            .contains("record 2: expected S9 start 0x1234, found error (checksum mismatch in S9 record at 0x1234: found 0xB7, computed 0xB6)"));
+    }
+
+    #[test]
+    #[should_panic(expected = "records differ")]
+    fn assert_srec_eq_different_records_panics() {
+        crate::assert_srec_eq!([s1(0x1234, &[0x00])], [s1(0x1234, &[0x01])]);
+    }
+
+    #[cfg(all(feature = "reader", feature = "writer"))]
+    #[test]
+    fn roundtrip_check_overlong_header_returns_err() {
+        let record = Record::S0("x".repeat(MAX_HEADER_LEN + 1));
+
+        let r = roundtrip_check(&record);
+
+        assert!(r.unwrap_err().contains("could not be encoded"));
+    }
+
+    #[cfg(all(feature = "reader", feature = "writer"))]
+    #[test]
+    fn roundtrip_check_unknown_record_returns_ok() {
+        let record = Record::Unknown {
+            t: 4,
+            bytes: vec![0x00, 0x00, 0xaa],
+        };
+
+        let r = roundtrip_check(&record);
+
+        assert_eq!(r, Ok(()));
+    }
+}
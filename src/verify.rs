@@ -0,0 +1,179 @@
+//! Byte by byte comparison of memory images, as done by device programmers
+//! when verifying a part
+use crate::image::Segment;
+use std::fmt;
+
+/// A byte which differs between two images
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Address of the byte
+    pub address: u32,
+    /// Byte in the expected image
+    pub expected: u8,
+    /// Byte in the actual (read back) image
+    pub actual: u8,
+}
+
+/// Every byte which differs between two images, in ascending address order
+///
+/// Displays as a listing in the style of a device programmer's verify output,
+/// one mismatch per line under a heading, followed by a count.
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::image::Segment;
+///
+/// let expected = [Segment {
+///     address: 0x1234,
+///     data: vec![0x00, 0x01, 0x02],
+/// }];
+/// let actual = [Segment {
+///     address: 0x1234,
+///     data: vec![0x00, 0x11],
+/// }];
+///
+/// let listing = srec::verify::compare_images(&expected, &actual, 0xff);
+///
+/// assert_eq!(
+///     listing.to_string(),
+///     "ADDRESS   EXP  READ\n\
+///      00001235  01   11\n\
+///      00001236  02   FF\n\
+///      2 mismatches\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct MismatchListing {
+    /// Bytes which differ
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl MismatchListing {
+    /// Returns true if the images matched
+    pub fn is_empty(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl fmt::Display for MismatchListing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ADDRESS   EXP  READ")?;
+
+        for m in &self.mismatches {
+            writeln!(
+                f,
+                "{:08X}  {:02X}   {:02X}",
+                m.address, m.expected, m.actual
+            )?;
+        }
+
+        match self.mismatches.len() {
+            1 => writeln!(f, "1 mismatch"),
+            n => writeln!(f, "{} mismatches", n),
+        }
+    }
+}
+
+/// Returns the byte at `address` in sorted, non-overlapping segments
+fn byte_at(segments: &[Segment], address: u32) -> Option<u8> {
+    let i = segments.partition_point(|s| s.end() <= address as u64);
+    let segment = segments.get(i)?;
+
+    address
+        .checked_sub(segment.address)
+        .and_then(|offset| segment.data.get(offset as usize).copied())
+}
+
+/// Compares two images byte by byte, returning every byte which differs
+///
+/// Each image is given as segments sorted by address which do not overlap, as
+/// returned by [`SrecFile::data_segments`](crate::SrecFile::data_segments).
+/// Every address written by either image is compared, taking bytes missing
+/// from the other image as `fill` (e.g. 0xFF for erased flash).
+pub fn compare_images(expected: &[Segment], actual: &[Segment], fill: u8) -> MismatchListing {
+    let mut ranges = expected
+        .iter()
+        .chain(actual)
+        .map(|s| (s.address as u64, s.end()))
+        .collect::<Vec<_>>();
+    ranges.sort_unstable();
+
+    let mut mismatches = Vec::new();
+    let mut next = 0;
+
+    for (start, end) in ranges {
+        for address in start.max(next)..end {
+            let address = address as u32;
+            let e = byte_at(expected, address).unwrap_or(fill);
+            let a = byte_at(actual, address).unwrap_or(fill);
+
+            if e != a {
+                mismatches.push(Mismatch {
+                    address,
+                    expected: e,
+                    actual: a,
+                });
+            }
+        }
+        next = next.max(end);
+    }
+
+    MismatchListing { mismatches }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(address: u32, data: &[u8]) -> Segment {
+        Segment {
+            address,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn compare_images_equal_images_returns_empty_listing() {
+        let image = [segment(0x10, &[0x00, 0x01]), segment(0x20, &[0x02])];
+
+        let listing = compare_images(&image, &image, 0xff);
+
+        assert!(listing.is_empty());
+        assert_eq!(listing.to_string(), "ADDRESS   EXP  READ\n0 mismatches\n");
+    }
+
+    #[test]
+    fn compare_images_fill_matches_missing_bytes() {
+        let expected = [segment(0x10, &[0xff, 0x01, 0xff])];
+        let actual = [segment(0x11, &[0x01]), segment(0x13, &[0x02])];
+
+        let listing = compare_images(&expected, &actual, 0xff);
+
+        assert_eq!(
+            listing.mismatches,
+            [Mismatch {
+                address: 0x13,
+                expected: 0xff,
+                actual: 0x02
+            }]
+        );
+    }
+
+    #[test]
+    fn compare_images_top_of_address_space_does_not_overflow() {
+        let expected = [segment(0xffff_fffe, &[0x00, 0x01])];
+        let actual = [segment(0xffff_ffff, &[0x00])];
+
+        let listing = compare_images(&expected, &actual, 0x00);
+
+        assert_eq!(
+            listing.mismatches,
+            [Mismatch {
+                address: 0xffff_ffff,
+                expected: 0x01,
+                actual: 0x00
+            }]
+        );
+    }
+}
@@ -1,39 +1,211 @@
 //! Generation of SREC records and files
 use crate::checksum::checksum_of;
 use crate::record::*;
+use std::collections::VecDeque;
+use std::error;
+use std::fmt::{self, Write};
+use std::iter;
 
-fn make_record(t: u8, address: &impl Address, data: &[u8]) -> String {
-    assert!(t < 10, "invalid record type {}", t);
+/// Errors which may occur during writing
+#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+pub enum Error {
+    /// More than one header (S0) record was provided
+    DuplicateHeader,
+    /// More than one count (S5/S6) record was provided
+    DuplicateCount,
+    /// More than one start address (S7/S8/S9) record was provided
+    DuplicateStartAddress,
+    /// Record type was not a single decimal digit
+    InvalidRecordType,
+    /// Record fields did not fit in the 255 bytes allowed by the byte count
+    RecordTooLong,
+    /// Count of an S6 record did not fit in 24 bits
+    CountTooLarge,
+    /// Generated file would have been larger than
+    /// [`WriterOptions::max_output_bytes`]
+    OutputTooLarge {
+        /// Size in bytes the file would have been
+        size: u64,
+        /// Largest size allowed
+        max: u64,
+    },
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::DuplicateHeader => write!(f, "duplicate header record"),
+            Error::DuplicateCount => write!(f, "duplicate count record"),
+            Error::DuplicateStartAddress => write!(f, "duplicate start address record"),
+            Error::InvalidRecordType => write!(f, "invalid record type"),
+            Error::RecordTooLong => write!(f, "record too long"),
+            Error::CountTooLarge => write!(f, "count too large"),
+            Error::OutputTooLarge { size, max } => write!(
+                f,
+                "output of {} bytes exceeds maximum of {} bytes",
+                size, max
+            ),
+        }
+    }
+}
+
+/// Appends the two uppercase hex digits of `b` to `out`
+fn push_hex(out: &mut String, b: u8) {
+    const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+    out.push(DIGITS[(b >> 4) as usize] as char);
+    out.push(DIGITS[(b & 0xf) as usize] as char);
+}
+
+/// Returns an error if a record of type `t` with the provided address and
+/// payload bytes cannot be encoded
+fn check_frame(t: u8, address: &[u8], data: &[u8]) -> Result<(), Error> {
+    if t > 9 {
+        return Err(Error::InvalidRecordType);
+    }
+
+    // The byte count covers the address, payload and checksum
+    if address.len() + data.len() + 1 > 0xff {
+        return Err(Error::RecordTooLong);
+    }
 
-    let mut bytes = vec![0x00];
-    bytes.extend(address.to_be_bytes());
-    bytes.extend(data);
-    // The length byte doesn't count itself, so subtract one for the length byte
-    // we saved space for when we created the bytes vec. Add one byte for the
-    // checksum that finishes the record.
-    bytes[0] = (bytes.len() - 1 + 1) as u8;
+    Ok(())
+}
+
+fn frame_to(out: &mut String, t: u8, address: &[u8], data: &[u8]) {
+    // The byte count doesn't count itself, but does include the checksum that
+    // finishes the record
+    let byte_count = (address.len() + data.len() + 1) as u8;
 
-    let bytes_str = bytes
-        .iter()
-        .map(|b| format!("{:02X}", b))
-        .collect::<Vec<_>>()
-        .join("");
+    out.reserve(4 + 2 * (address.len() + data.len() + 1));
+    let _ = write!(out, "S{}", t);
+    push_hex(out, byte_count);
+    for &b in address.iter().chain(data) {
+        push_hex(out, b);
+    }
+    push_hex(
+        out,
+        checksum_of(iter::once(&byte_count).chain(address).chain(data)),
+    );
+}
+
+fn frame(t: u8, address: &[u8], data: &[u8]) -> String {
+    let mut s = String::new();
+    frame_to(&mut s, t, address, data);
+    s
+}
+
+/// Checks and then frames a record, leaving `out` unchanged on error
+fn encode_frame_to(out: &mut String, t: u8, address: &[u8], data: &[u8]) -> Result<(), Error> {
+    check_frame(t, address, data)?;
+    frame_to(out, t, address, data);
+    Ok(())
+}
 
-    format!("S{}{}{:02X}", t, bytes_str, checksum_of(&bytes))
+fn make_record_to(
+    out: &mut String,
+    t: u8,
+    address: &impl Address,
+    data: &[u8],
+) -> Result<(), Error> {
+    encode_frame_to(out, t, &address.to_be_bytes(), data)
+}
+
+/// Encodes record fields into a line with SREC framing: "S", the type digit,
+/// the byte count, the address and payload bytes in hex and the checksum
+///
+/// Applies no meaning to the address or payload, so can be used to build
+/// vendor-specific records which keep the standard framing.
+///
+/// # Examples
+///
+/// ```rust
+/// let line = srec::writer::LineEncoder::new().encode(1, &[0x12, 0x34], &[0x00, 0x01, 0x02, 0x03]);
+///
+/// assert_eq!(line, Ok("S107123400010203AC".into()));
+/// ```
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub struct LineEncoder;
+
+impl LineEncoder {
+    /// Creates a new encoder
+    pub fn new() -> Self {
+        LineEncoder
+    }
+
+    /// Encodes a record of type `t` (0 to 9) with the provided address and
+    /// payload bytes, without a line ending
+    ///
+    /// Returns an error if `t` is not a single digit, or if the address,
+    /// payload and checksum together are longer than 255 bytes.
+    pub fn encode(&self, t: u8, address: &[u8], payload: &[u8]) -> Result<String, Error> {
+        check_frame(t, address, payload)?;
+
+        Ok(frame(t, address, payload))
+    }
+}
+
+impl RawRecord {
+    /// Encodes the record with SREC framing, without a line ending
+    ///
+    /// Returns an error if the type is not a single digit, or if the payload
+    /// and checksum together are longer than 255 bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let rr = srec::RawRecord {
+    ///     t: 4,
+    ///     bytes: vec![0x00, 0x00, 0xaa, 0xbb],
+    /// };
+    ///
+    /// assert_eq!(rr.encode(), Ok("S4050000AABB95".into()));
+    /// ```
+    pub fn encode(&self) -> Result<String, Error> {
+        LineEncoder::new().encode(self.t, &[], &self.bytes)
+    }
 }
 
 impl Record {
+    #[cfg(test)]
     fn encode(&self) -> String {
+        let mut s = String::new();
+        self.encode_to(&mut s).unwrap();
+        s
+    }
+
+    /// Encodes the record and appends it to `out`, without a line ending
+    ///
+    /// Lets many records be written into one buffer without allocating a
+    /// string for each. Returns an error, leaving `out` unchanged, if the
+    /// record's type is not a single digit, its fields are longer than the 255
+    /// bytes allowed by the byte count, or it is an S6 record whose count does
+    /// not fit in 24 bits.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut s = String::new();
+    ///
+    /// srec::Record::S9(srec::Address16(0x1234)).encode_to(&mut s).unwrap();
+    ///
+    /// assert_eq!(s, "S9031234B6");
+    /// ```
+    pub fn encode_to(&self, out: &mut String) -> Result<(), Error> {
         match self {
-            Record::S0(s) => make_record(0, &Address16(0x0000), &s.bytes().collect::<Vec<_>>()),
-            Record::S1(Data { address, data }) => make_record(1, address, data),
-            Record::S2(Data { address, data }) => make_record(2, address, data),
-            Record::S3(Data { address, data }) => make_record(3, address, data),
-            Record::S5(Count16(c)) => make_record(5, &Address16(*c), &[]),
-            Record::S6(Count24(c)) => make_record(6, &Address24(*c), &[]),
-            Record::S7(address) => make_record(7, address, &[]),
-            Record::S8(address) => make_record(8, address, &[]),
-            Record::S9(address) => make_record(9, address, &[]),
+            Record::S0(s) => make_record_to(out, 0, &Address16(0x0000), s.as_bytes()),
+            Record::S1(Data { address, data }) => make_record_to(out, 1, address, data),
+            Record::S2(Data { address, data }) => make_record_to(out, 2, address, data),
+            Record::S3(Data { address, data }) => make_record_to(out, 3, address, data),
+            Record::S5(Count16(c)) => make_record_to(out, 5, &Address16(*c), &[]),
+            Record::S6(Count24(c)) if *c > 0xff_ffff => Err(Error::CountTooLarge),
+            Record::S6(Count24(c)) => make_record_to(out, 6, &Address24(*c), &[]),
+            Record::S7(address) => make_record_to(out, 7, address, &[]),
+            Record::S8(address) => make_record_to(out, 8, address, &[]),
+            Record::S9(address) => make_record_to(out, 9, address, &[]),
+            Record::Unknown { t, bytes } => encode_frame_to(out, *t, &[], bytes),
         }
     }
 }
@@ -41,7 +213,8 @@ impl Record {
 /// Converts each provided record to a string, joining them with newlines ('\n')
 /// to generate an LF terminated SREC file
 ///
-/// Does not perform any validation on the provided records. The caller is
+/// Returns the first error from [`Record::encode_to`] if a record cannot be
+/// encoded. Does not otherwise validate the provided records. The caller is
 /// responsible for ensuring records do not contain duplicate/overlapping data
 /// and that records are in the correct order.
 ///
@@ -63,19 +236,463 @@ impl Record {
 ///
 /// assert_eq!(
 ///     s,
+///     Ok("S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n".into())
+/// );
+/// ```
+pub fn generate_srec_file(records: &[Record]) -> Result<String, Error> {
+    generate_srec_file_with(records, &WriterOptions::default())
+}
+
+/// Options controlling how [`generate_srec_file_with`] generates a file
+///
+/// The default options match the behaviour of [`generate_srec_file`].
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
+pub struct WriterOptions {
+    /// Write a comment line before each region of contiguous data, giving its
+    /// address range (e.g. "; region 0x08000000..0x08003FFF"), so large files
+    /// can be navigated in a text editor
+    ///
+    /// Comment lines are not part of the format. They are skipped when reading
+    /// with [`ReaderOptions::skip_non_records`](crate::reader::ReaderOptions::skip_non_records),
+    /// and by other tolerant tools.
+    pub region_banners: bool,
+    /// Largest file, in bytes, which [`generate_srec_file_with`] will
+    /// generate. Larger files are reported as [`Error::OutputTooLarge`] before
+    /// anything is generated, so e.g. an accidental fill of a huge address span
+    /// cannot produce a pathological artifact.
+    pub max_output_bytes: Option<u64>,
+    /// Largest number of data bytes to write in each data record (S1, S2 or
+    /// S3). Longer data records are split into records of this length at
+    /// consecutive addresses, widened to S2 or S3 where an address no longer
+    /// fits. A length of 0 is treated as 1.
+    pub record_length: Option<usize>,
+    /// End lines with "\r\n" rather than "\n"
+    pub crlf: bool,
+}
+
+impl WriterOptions {
+    /// Sets [`region_banners`](Self::region_banners), returning the options
+    /// so calls can be chained
+    pub fn with_region_banners(mut self, region_banners: bool) -> Self {
+        self.region_banners = region_banners;
+        self
+    }
+
+    /// Sets [`max_output_bytes`](Self::max_output_bytes), returning the
+    /// options so calls can be chained
+    pub fn with_max_output_bytes(mut self, max_output_bytes: Option<u64>) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// Sets [`record_length`](Self::record_length) to `record_length`,
+    /// returning the options so calls can be chained
+    pub fn with_record_length(mut self, record_length: usize) -> Self {
+        self.record_length = Some(record_length);
+        self
+    }
+
+    /// Sets [`crlf`](Self::crlf), returning the options so calls can be
+    /// chained
+    pub fn with_crlf(mut self, crlf: bool) -> Self {
+        self.crlf = crlf;
+        self
+    }
+
+    fn line_ending(&self) -> &'static str {
+        if self.crlf {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+}
+
+/// Calls `f` with each record to write for `record`, splitting data records
+/// longer than `record_length` into several
+fn for_each_split(
+    record: &Record,
+    record_length: Option<usize>,
+    mut f: impl FnMut(&Record) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let (address, data) = match (record_length, record.memory_effect()) {
+        (Some(len), MemoryEffect::Write { address, data }) if data.len() > len.max(1) => {
+            (address, data)
+        }
+        _ => return f(record),
+    };
+    let len = record_length.unwrap_or(1).max(1);
+
+    for (i, chunk) in data.chunks(len).enumerate() {
+        let address = address.wrapping_add((i * len) as u32);
+        let data = chunk.to_vec();
+
+        f(&match record {
+            Record::S1(_) if address <= 0xffff => Record::S1(Data {
+                address: Address16(address as u16),
+                data,
+            }),
+            Record::S1(_) | Record::S2(_) if address <= 0xff_ffff => Record::S2(Data {
+                address: Address24(address),
+                data,
+            }),
+            _ => Record::S3(Data {
+                address: Address32(address),
+                data,
+            }),
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Returns the address range written by a data record, if it writes anything
+fn data_range(record: &Record) -> Option<(u32, u64)> {
+    match record.memory_effect() {
+        MemoryEffect::Write { address, data } if !data.is_empty() => {
+            Some((address, address as u64 + data.len() as u64))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the end of the region of contiguous data written by the data
+/// records at the start of `records`
+fn region_end(records: &[Record]) -> u64 {
+    let mut end = 0;
+
+    for (i, record) in records.iter().enumerate() {
+        match data_range(record) {
+            Some((start, next_end)) if i == 0 || start as u64 == end => end = next_end,
+            _ => break,
+        }
+    }
+
+    end
+}
+
+/// Converts each provided record to a string using the provided options,
+/// ending each line with "\n", or "\r\n" if [`WriterOptions::crlf`] is set
+///
+/// Data records longer than [`WriterOptions::record_length`] are split. If
+/// [`WriterOptions::max_output_bytes`] is set, the size of the file is checked
+/// with [`estimate_output_size`] before anything is generated. Otherwise
+/// behaves exactly as [`generate_srec_file`].
+///
+/// # Examples
+///
+/// ```rust
+/// use srec::writer::WriterOptions;
+///
+/// let s = srec::writer::generate_srec_file_with(
+///     &[
+///         srec::Record::S1(srec::Data {
+///             address: srec::Address16(0x1234),
+///             data: vec![0x00, 0x01, 0x02, 0x03],
+///         }),
+///         srec::Record::S1(srec::Data {
+///             address: srec::Address16(0x1238),
+///             data: vec![0x04, 0x05, 0x06, 0x07],
+///         }),
+///         srec::Record::S9(srec::Address16(0x1234)),
+///     ],
+///     &WriterOptions::default().with_region_banners(true),
+/// );
+///
+/// assert_eq!(
+///     s.unwrap(),
+///     "; region 0x00001234..0x0000123B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n"
+/// );
+///
+/// let options = WriterOptions::default().with_max_output_bytes(Some(8));
+/// let s = srec::writer::generate_srec_file_with(&[srec::Record::S9(srec::Address16(0x1234))], &options);
+///
+/// assert_eq!(
+///     s,
+///     Err(srec::WriterError::OutputTooLarge { size: 11, max: 8 })
+/// );
+///
+/// let options = WriterOptions::default().with_record_length(2).with_crlf(true);
+/// let s = srec::writer::generate_srec_file_with(
+///     &[srec::Record::S1(srec::Data {
+///         address: srec::Address16(0x1234),
+///         data: vec![0x00, 0x01, 0x02],
+///     })],
+///     &options,
+/// );
+///
+/// assert_eq!(s.unwrap(), "S10512340001B3\r\nS104123602B1\r\n");
+/// ```
+pub fn generate_srec_file_with(
+    records: &[Record],
+    options: &WriterOptions,
+) -> Result<String, Error> {
+    let size = estimate_output_size(records, options);
+    if let Some(max) = options.max_output_bytes {
+        if size > max {
+            return Err(Error::OutputTooLarge { size, max });
+        }
+    }
+
+    // The estimate is exact for records which can be encoded, so the output is
+    // built without reallocating
+    let mut s = String::with_capacity(size as usize);
+    let mut last_end = None;
+    let line_ending = options.line_ending();
+
+    for (i, record) in records.iter().enumerate() {
+        if options.region_banners {
+            if let Some((start, end)) = region_banner(records, i, &mut last_end) {
+                let _ = write!(s, "; region {:#010X}..{:#010X}", start, end - 1);
+                s.push_str(line_ending);
+            }
+        }
+
+        for_each_split(record, options.record_length, |record| {
+            record.encode_to(&mut s)?;
+            s.push_str(line_ending);
+            Ok(())
+        })?;
+    }
+
+    Ok(s)
+}
+
+/// Returns the region to write a banner for before `records[i]`, if it starts
+/// a new region, given the end of the data written by the record before it
+fn region_banner(records: &[Record], i: usize, last_end: &mut Option<u64>) -> Option<(u32, u64)> {
+    let range = data_range(&records[i]);
+    let banner = match range {
+        Some((start, _)) if *last_end != Some(start as u64) => {
+            Some((start, region_end(&records[i..])))
+        }
+        _ => None,
+    };
+
+    *last_end = range.map(|(_, end)| end);
+    banner
+}
+
+/// Length of a region banner line, without its line ending
+const REGION_BANNER_LEN: u64 = "; region 0x00000000..0x00000000".len() as u64;
+
+/// Returns the length of the line encoding `record`, without its line ending
+fn encoded_len(record: &Record) -> u64 {
+    let payload = match record {
+        Record::S0(s) => 2 + s.len(),
+        Record::S1(Data { data, .. }) => 2 + data.len(),
+        Record::S2(Data { data, .. }) => 3 + data.len(),
+        Record::S3(Data { data, .. }) => 4 + data.len(),
+        Record::S5(_) | Record::S9(_) => 2,
+        Record::S6(_) | Record::S8(_) => 3,
+        Record::S7(_) => 4,
+        Record::Unknown { bytes, .. } => bytes.len(),
+    };
+
+    // "S", the type and the byte count, then the payload and checksum in hex
+    4 + 2 * (payload as u64 + 1)
+}
+
+/// Returns the size in bytes of the file [`generate_srec_file_with`] would
+/// generate from `records`, without generating it
+///
+/// Records which cannot be encoded are counted as if they could.
+///
+/// # Examples
+///
+/// ```rust
+/// let records = [
+///     srec::Record::S1(srec::Data {
+///         address: srec::Address16(0x1234),
+///         data: vec![0x00, 0x01, 0x02, 0x03],
+///     }),
+///     srec::Record::S9(srec::Address16(0x1234)),
+/// ];
+///
+/// let size = srec::writer::estimate_output_size(&records, &Default::default());
+///
+/// assert_eq!(size, 30);
+/// ```
+pub fn estimate_output_size(records: &[Record], options: &WriterOptions) -> u64 {
+    let mut size = 0;
+    let mut last_end = None;
+    let line_ending = options.line_ending().len() as u64;
+
+    for (i, record) in records.iter().enumerate() {
+        if options.region_banners && region_banner(records, i, &mut last_end).is_some() {
+            size += REGION_BANNER_LEN + line_ending;
+        }
+
+        let _ = for_each_split(record, options.record_length, |record| {
+            size += encoded_len(record) + line_ending;
+            Ok(())
+        });
+    }
+
+    size
+}
+
+/// Generates an SREC file holding several modules, one after another, as
+/// read by [`read_modules`](crate::reader::read_modules)
+///
+/// Each module's records are given by [`Module::to_records`]. Returns the first
+/// error from [`Record::encode_to`] if a record cannot be encoded, but does not
+/// otherwise validate the modules.
+///
+/// # Examples
+///
+/// ```rust
+/// let modules = [
+///     srec::Module {
+///         header: Some("HDR".into()),
+///         start: Some(0x1234),
+///         ..Default::default()
+///     },
+///     srec::Module {
+///         count: Some(0),
+///         ..Default::default()
+///     },
+/// ];
+///
+/// let s = srec::writer::generate_modules(&modules);
+///
+/// assert_eq!(s, Ok("S00600004844521B\nS9031234B6\nS5030000FC\n".into()));
+/// ```
+pub fn generate_modules(modules: &[Module]) -> Result<String, Error> {
+    let mut s = String::new();
+
+    for module in modules {
+        for record in module.to_records() {
+            record.encode_to(&mut s)?;
+            s.push('\n');
+        }
+    }
+
+    Ok(s)
+}
+
+/// Iterator adapter which yields records in a valid file order as they are
+/// produced: the header, then data records and any records of unknown type,
+/// then the count record, then the start address record
+///
+/// Lets streaming producers emit the header, count and start address whenever
+/// convenient. Records read before the header are held back until it arrives,
+/// after which records pass straight through; a producer which writes the
+/// header first is never buffered. If there is no header, everything read is
+/// held until the input ends. The count and start address records are held
+/// until the input ends. Data records keep their input order, as sorting them
+/// would need the whole input.
+///
+/// If the input contains more than one header, count or start address record
+/// there is no valid order, and the iterator yields an error and stops.
+///
+/// # Examples
+///
+/// ```rust
+/// let records = srec::writer::OrderedRecords::new(vec![
+///     srec::Record::S9(srec::Address16(0x1234)),
+///     srec::Record::S0("HDR".into()),
+///     srec::Record::S1(srec::Data {
+///         address: srec::Address16(0x1234),
+///         data: vec![0x00, 0x01, 0x02, 0x03],
+///     }),
+///     srec::Record::S1(srec::Data {
+///         address: srec::Address16(0x1238),
+///         data: vec![0x04, 0x05, 0x06, 0x07],
+///     }),
+/// ])
+/// .collect::<Result<Vec<_>, _>>()
+/// .unwrap();
+///
+/// assert_eq!(
+///     srec::generate_srec_file(&records).unwrap(),
 ///     "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n"
 /// );
 /// ```
-pub fn generate_srec_file(records: &[Record]) -> String {
-    records
-        .iter()
-        .map(Record::encode)
-        .map(|s| {
-            let mut s2 = s.clone();
-            s2.push('\n');
-            s2
-        })
-        .collect()
+#[derive(Debug)]
+pub struct OrderedRecords<I> {
+    input: Option<I>,
+    /// Records read before the header
+    held: VecDeque<Record>,
+    header_seen: bool,
+    count: Option<Record>,
+    start_address: Option<Record>,
+}
+
+impl<I: Iterator<Item = Record>> OrderedRecords<I> {
+    /// Creates an adapter ordering the records from `records`
+    pub fn new(records: impl IntoIterator<IntoIter = I>) -> Self {
+        OrderedRecords {
+            input: Some(records.into_iter()),
+            held: VecDeque::new(),
+            header_seen: false,
+            count: None,
+            start_address: None,
+        }
+    }
+
+    fn fail(&mut self, err: Error) -> Option<Result<Record, Error>> {
+        self.input = None;
+        self.held.clear();
+        self.count = None;
+        self.start_address = None;
+        Some(Err(err))
+    }
+}
+
+impl<I: Iterator<Item = Record>> Iterator for OrderedRecords<I> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.header_seen {
+                if let Some(record) = self.held.pop_front() {
+                    return Some(Ok(record));
+                }
+            }
+
+            let input = match &mut self.input {
+                Some(input) => input,
+                None => break,
+            };
+
+            let record = match input.next() {
+                Some(record) => record,
+                None => {
+                    // Without a header, the held records go first
+                    self.input = None;
+                    self.header_seen = true;
+                    continue;
+                }
+            };
+
+            let (slot, err) = match record {
+                Record::S0(_) if self.header_seen => return self.fail(Error::DuplicateHeader),
+                Record::S0(_) => {
+                    self.header_seen = true;
+                    return Some(Ok(record));
+                }
+                Record::S5(_) | Record::S6(_) => (&mut self.count, Error::DuplicateCount),
+                Record::S7(_) | Record::S8(_) | Record::S9(_) => {
+                    (&mut self.start_address, Error::DuplicateStartAddress)
+                }
+                _ if self.header_seen => return Some(Ok(record)),
+                _ => {
+                    self.held.push_back(record);
+                    continue;
+                }
+            };
+
+            if slot.replace(record).is_some() {
+                return self.fail(err);
+            }
+        }
+
+        self.count
+            .take()
+            .or_else(|| self.start_address.take())
+            .map(Ok)
+    }
 }
 
 #[cfg(test)]
@@ -217,13 +834,25 @@ mod tests {
         assert_eq!(s, "S9031234B6");
     }
 
+    #[test]
+    fn encode_unknown_returns_correct_record() {
+        let r = Record::Unknown {
+            t: 4,
+            bytes: vec![0x00, 0x00],
+        };
+
+        let s = r.encode();
+
+        assert_eq!(s, "S4030000FC");
+    }
+
     #[test]
     fn generate_srec_file_empty_list_returns_empty_string() {
         let r = [];
 
         let s = generate_srec_file(&r);
 
-        assert_eq!(s, "");
+        assert_eq!(s, Ok("".into()));
     }
 
     #[test]
@@ -232,7 +861,7 @@ mod tests {
 
         let s = generate_srec_file(&r);
 
-        assert_eq!(s, "S00600004844521B\n");
+        assert_eq!(s, Ok("S00600004844521B\n".into()));
     }
 
     #[test]
@@ -254,7 +883,358 @@ mod tests {
 
         assert_eq!(
             s,
-            "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n"
+            Ok("S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n".into())
+        );
+    }
+
+    #[test]
+    fn ordered_records_empty_input_returns_empty_iterator() {
+        let mut ri = OrderedRecords::new(vec![]);
+
+        assert_eq!(ri.next(), None);
+    }
+
+    #[test]
+    fn ordered_records_unordered_input_returns_records_in_file_order() {
+        let r = vec![
+            Record::S9(Address16(0x1234)),
+            Record::S5(Count16(0x0003)),
+            Record::S3(Data {
+                address: Address32(0x00002000),
+                data: vec![0x02],
+            }),
+            Record::S1(Data {
+                address: Address16(0x1000),
+                data: vec![0x00],
+            }),
+            Record::S0("HDR".into()),
+            Record::S2(Data {
+                address: Address24(0x001000),
+                data: vec![0x01],
+            }),
+        ];
+
+        let ri = OrderedRecords::new(r).collect::<Vec<_>>();
+
+        assert_eq!(
+            ri,
+            vec![
+                Ok(Record::S0("HDR".into())),
+                Ok(Record::S3(Data {
+                    address: Address32(0x00002000),
+                    data: vec![0x02],
+                })),
+                Ok(Record::S1(Data {
+                    address: Address16(0x1000),
+                    data: vec![0x00],
+                })),
+                Ok(Record::S2(Data {
+                    address: Address24(0x001000),
+                    data: vec![0x01],
+                })),
+                Ok(Record::S5(Count16(0x0003))),
+                Ok(Record::S9(Address16(0x1234))),
+            ]
+        );
+    }
+
+    #[test]
+    fn ordered_records_duplicate_header_returns_err_duplicate_header() {
+        let r = vec![Record::S0("HDR".into()), Record::S0("HDR".into())];
+
+        let ri = OrderedRecords::new(r).collect::<Vec<_>>();
+
+        assert_eq!(
+            ri,
+            vec![Ok(Record::S0("HDR".into())), Err(Error::DuplicateHeader)]
+        );
+    }
+
+    #[test]
+    fn ordered_records_after_header_yields_records_without_reading_ahead() {
+        let r = vec![
+            Record::S0("HDR".into()),
+            Record::S1(Data {
+                address: Address16(0x1000),
+                data: vec![0x00],
+            }),
+        ];
+        let input = r
+            .into_iter()
+            .chain(iter::from_fn(|| panic!("read past the records needed")));
+
+        let ri = OrderedRecords::new(input).take(2).collect::<Vec<_>>();
+
+        assert_eq!(ri.len(), 2);
+    }
+
+    #[test]
+    fn ordered_records_without_header_yields_records_in_input_order() {
+        let r = vec![
+            Record::S9(Address16(0x1234)),
+            Record::S1(Data {
+                address: Address16(0x1000),
+                data: vec![0x00],
+            }),
+        ];
+
+        let ri = OrderedRecords::new(r).collect::<Vec<_>>();
+
+        assert_eq!(
+            ri,
+            vec![
+                Ok(Record::S1(Data {
+                    address: Address16(0x1000),
+                    data: vec![0x00],
+                })),
+                Ok(Record::S9(Address16(0x1234))),
+            ]
+        );
+    }
+
+    #[test]
+    fn ordered_records_duplicate_count_returns_err_duplicate_count() {
+        let r = vec![Record::S5(Count16(0x0000)), Record::S6(Count24(0x000000))];
+
+        let ri = OrderedRecords::new(r).collect::<Vec<_>>();
+
+        assert_eq!(ri, vec![Err(Error::DuplicateCount)]);
+    }
+
+    #[test]
+    fn ordered_records_duplicate_start_address_returns_err_duplicate_start_address() {
+        let r = vec![
+            Record::S9(Address16(0x0000)),
+            Record::S7(Address32(0x00000000)),
+        ];
+
+        let ri = OrderedRecords::new(r).collect::<Vec<_>>();
+
+        assert_eq!(ri, vec![Err(Error::DuplicateStartAddress)]);
+    }
+
+    #[test]
+    fn line_encoder_encode_returns_framed_record() {
+        let e = LineEncoder::new();
+
+        let s = e.encode(3, &[0x12, 0x34, 0x56, 0x78], &[0x00, 0x01, 0x02, 0x03]);
+
+        assert_eq!(s, Ok("S3091234567800010203DC".into()));
+    }
+
+    #[test]
+    fn line_encoder_encode_empty_fields_returns_checksum_only_record() {
+        let e = LineEncoder::new();
+
+        let s = e.encode(4, &[], &[]);
+
+        assert_eq!(s, Ok("S401FE".into()));
+    }
+
+    #[test]
+    fn line_encoder_encode_maximum_length_returns_framed_record() {
+        let e = LineEncoder::new();
+
+        let s = e.encode(1, &[0x00, 0x00], &[0x00; 252]).unwrap();
+
+        assert_eq!(s.len(), 2 + 2 * 256);
+        assert!(s.starts_with("S1FF"));
+    }
+
+    #[test]
+    fn line_encoder_encode_invalid_type_returns_err_invalid_record_type() {
+        let e = LineEncoder::new();
+
+        let s = e.encode(10, &[], &[]);
+
+        assert_eq!(s, Err(Error::InvalidRecordType));
+    }
+
+    #[test]
+    fn line_encoder_encode_too_long_returns_err_record_too_long() {
+        let e = LineEncoder::new();
+
+        let s = e.encode(1, &[0x00, 0x00], &[0x00; 253]);
+
+        assert_eq!(s, Err(Error::RecordTooLong));
+    }
+
+    #[test]
+    fn raw_record_encode_too_long_returns_err_record_too_long() {
+        let rr = RawRecord {
+            t: 4,
+            bytes: vec![0x00; 255],
+        };
+
+        let s = rr.encode();
+
+        assert_eq!(s, Err(Error::RecordTooLong));
+    }
+
+    #[test]
+    fn encode_to_data_too_long_returns_err_record_too_long() {
+        let r = Record::S1(Data {
+            address: Address16(0x1234),
+            data: vec![0x00; 253],
+        });
+        let mut s = String::new();
+
+        let result = r.encode_to(&mut s);
+
+        assert_eq!(result, Err(Error::RecordTooLong));
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn encode_to_unknown_type_above_9_returns_err_invalid_record_type() {
+        let r = Record::Unknown {
+            t: 10,
+            bytes: vec![0x00, 0x00],
+        };
+        let mut s = String::new();
+
+        let result = r.encode_to(&mut s);
+
+        assert_eq!(result, Err(Error::InvalidRecordType));
+    }
+
+    #[test]
+    fn generate_modules_count_above_24_bits_returns_err_count_too_large() {
+        let modules = [Module {
+            count: Some(0x100_0000),
+            ..Default::default()
+        }];
+
+        let s = generate_modules(&modules);
+
+        assert_eq!(s, Err(Error::CountTooLarge));
+    }
+
+    #[test]
+    fn generate_srec_file_with_region_banners_marks_each_region() {
+        let records = [
+            Record::S0("HDR".into()),
+            Record::S3(Data {
+                address: Address32(0xffff_fffe),
+                data: vec![0x00],
+            }),
+            Record::S3(Data {
+                address: Address32(0xffff_ffff),
+                data: vec![0x01],
+            }),
+            Record::S3(Data {
+                address: Address32(0x0000_0000),
+                data: vec![0x02],
+            }),
+        ];
+        let options = WriterOptions {
+            region_banners: true,
+            ..Default::default()
+        };
+
+        let s = generate_srec_file_with(&records, &options).unwrap();
+
+        assert_eq!(
+            s.lines()
+                .filter(|line| line.starts_with(';'))
+                .collect::<Vec<_>>(),
+            [
+                "; region 0xFFFFFFFE..0xFFFFFFFF",
+                "; region 0x00000000..0x00000000"
+            ]
+        );
+    }
+
+    #[test]
+    fn estimate_output_size_matches_generated_file() {
+        let records = [
+            Record::S0("HDR".into()),
+            Record::S2(Data {
+                address: Address24(0x12_3456),
+                data: vec![0x00; 32],
+            }),
+            Record::S3(Data {
+                address: Address32(0x0012_3476),
+                data: vec![0x01; 3],
+            }),
+            Record::S1(Data {
+                address: Address16(0x0000),
+                data: vec![],
+            }),
+            Record::Unknown {
+                t: 4,
+                bytes: vec![0xaa, 0xbb],
+            },
+            Record::S6(Count24(3)),
+            Record::S7(Address32(0x0012_3456)),
+        ];
+
+        for &region_banners in &[false, true] {
+            for &crlf in &[false, true] {
+                for &record_length in &[None, Some(0), Some(16)] {
+                    let options = WriterOptions {
+                        record_length,
+                        ..WriterOptions::default()
+                            .with_region_banners(region_banners)
+                            .with_crlf(crlf)
+                    };
+
+                    let size = estimate_output_size(&records, &options);
+
+                    assert_eq!(
+                        size,
+                        generate_srec_file_with(&records, &options).unwrap().len() as u64
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generate_srec_file_with_record_length_splits_and_widens_data_records() {
+        let records = [Record::S1(Data {
+            address: Address16(0xfffe),
+            data: vec![0x00, 0x01, 0x02, 0x03],
+        })];
+        let options = WriterOptions::default().with_record_length(2);
+
+        let s = generate_srec_file_with(&records, &options);
+
+        assert_eq!(
+            s,
+            Ok([
+                Record::S1(Data {
+                    address: Address16(0xfffe),
+                    data: vec![0x00, 0x01],
+                })
+                .encode(),
+                Record::S2(Data {
+                    address: Address24(0x01_0000),
+                    data: vec![0x02, 0x03],
+                })
+                .encode(),
+                "".into(),
+            ]
+            .join("\n"))
         );
     }
+
+    #[test]
+    fn generate_srec_file_with_crlf_ends_every_line_with_crlf() {
+        let records = [
+            Record::S1(Data {
+                address: Address16(0x1234),
+                data: vec![0x00],
+            }),
+            Record::S9(Address16(0x1234)),
+        ];
+        let options = WriterOptions::default()
+            .with_region_banners(true)
+            .with_crlf(true);
+
+        let s = generate_srec_file_with(&records, &options).unwrap();
+
+        assert_eq!(s.matches("\r\n").count(), 3);
+        assert_eq!(s.matches('\n').count(), 3);
+    }
 }
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_borrow)]
+
 #[test]
 fn test_read_lf() {
     let s = "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n";
@@ -77,7 +79,12 @@ fn test_read_lf_with_err() {
     );
     assert_eq!(
         records.next(),
-        Some(Err(srec::reader::Error::ChecksumMismatch))
+        Some(Err(srec::reader::Error::ChecksumMismatch {
+            t: 9,
+            address: Some(0x1234),
+            found: 0xb4,
+            computed: 0xb6,
+        }))
     );
     assert_eq!(records.next(), None);
 }
@@ -105,7 +112,12 @@ fn test_read_crlf_with_err() {
     );
     assert_eq!(
         records.next(),
-        Some(Err(srec::reader::Error::ChecksumMismatch))
+        Some(Err(srec::reader::Error::ChecksumMismatch {
+            t: 9,
+            address: Some(0x1234),
+            found: 0xb4,
+            computed: 0xb6,
+        }))
     );
     assert_eq!(records.next(), None);
 }
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_borrow)]
+
 #[test]
 fn test_write_read() {
     let records = vec![
@@ -13,7 +15,7 @@ fn test_write_read() {
         srec::Record::S9(srec::Address16(0x1234)),
     ];
 
-    let records2 = srec::reader::read_records(&srec::writer::generate_srec_file(&records))
+    let records2 = srec::reader::read_records(&srec::writer::generate_srec_file(&records).unwrap())
         .map(Result::unwrap)
         .collect::<Vec<_>>();
 
@@ -28,7 +30,99 @@ fn test_read_write() {
         &srec::reader::read_records(&s)
             .map(Result::unwrap)
             .collect::<Vec<_>>(),
-    );
+    )
+    .unwrap();
+
+    assert_eq!(s, s2);
+}
+
+#[test]
+fn test_padded_header_round_trip() {
+    let header = srec::HeaderString::padded("HDR", 20).unwrap();
+
+    let s = srec::writer::generate_srec_file(&[header.into()]).unwrap();
+    let records = srec::reader::read_records(&s)
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    assert_eq!(records, vec![srec::Record::S0("HDR".into())]);
+}
+
+#[test]
+fn test_raw_record_round_trip() {
+    let s = "S4050000AABB95";
+
+    let s2 = srec::reader::raw_record_from_str(s).unwrap().encode();
+
+    assert_eq!(s2, Ok(s.into()));
+}
+
+#[test]
+fn test_boundary_records_round_trip() {
+    let records = vec![
+        srec::Record::S0(String::new()),
+        srec::Record::S0("x".repeat(srec::MAX_HEADER_LEN)),
+        srec::Record::S1(srec::Data {
+            address: srec::Address16(0),
+            data: vec![],
+        }),
+        srec::Record::S1(srec::Data {
+            address: srec::Address16(0xffff),
+            data: vec![0xff; 252],
+        }),
+        srec::Record::S2(srec::Data {
+            address: srec::Address24(0xff_ffff),
+            data: vec![0x00; 251],
+        }),
+        srec::Record::S3(srec::Data {
+            address: srec::Address32(0xffff_ffff),
+            data: (0..250).map(|b| b as u8).collect(),
+        }),
+        srec::Record::S5(srec::Count16(0)),
+        srec::Record::S5(srec::Count16(0xffff)),
+        srec::Record::S6(srec::Count24(0xff_ffff)),
+        srec::Record::S7(srec::Address32(0xffff_ffff)),
+        srec::Record::S8(srec::Address24(0xff_ffff)),
+        srec::Record::S9(srec::Address16(0)),
+    ];
+
+    let s = srec::writer::generate_srec_file(&records).unwrap();
+    let records2 = srec::reader::read_records(&s)
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    let s2 = srec::writer::generate_srec_file(&records2).unwrap();
 
+    assert_eq!(records, records2);
     assert_eq!(s, s2);
 }
+
+#[test]
+fn test_region_banners_skipped_when_reading() {
+    let records = vec![
+        srec::Record::S1(srec::Data {
+            address: srec::Address16(0x1234),
+            data: vec![0x00, 0x01, 0x02, 0x03],
+        }),
+        srec::Record::S1(srec::Data {
+            address: srec::Address16(0x2000),
+            data: vec![0x04, 0x05, 0x06, 0x07],
+        }),
+        srec::Record::S9(srec::Address16(0x1234)),
+    ];
+    let options = srec::reader::ReaderOptions {
+        skip_non_records: true,
+        ..Default::default()
+    };
+
+    let s = srec::writer::generate_srec_file_with(
+        &records,
+        &srec::writer::WriterOptions::default().with_region_banners(true),
+    )
+    .unwrap();
+    let records2 = srec::reader::read_records_with(&s, &options)
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    assert_eq!(s.matches("; region").count(), 2);
+    assert_eq!(records, records2);
+}
@@ -14,7 +14,7 @@ fn test_write() {
     ];
     let s = "S00600004844521B\nS107123400010203AC\nS10712380405060798\nS9031234B6\n";
 
-    let s2 = srec::writer::generate_srec_file(&records);
+    let s2 = srec::writer::generate_srec_file(&records).unwrap();
 
     assert_eq!(s, s2);
 }